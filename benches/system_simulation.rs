@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use nalgebra::Vector3;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::ThreadPoolBuilder;
+use space_build::world::{
+    body::{Body, BodyType},
+    galaxy::update_systems,
+    system::{CenterType, System},
+};
+use uuid::Uuid;
+
+const SYSTEM_COUNT: usize = 2000;
+const BODIES_PER_SYSTEM: usize = 200;
+const TICKS: usize = 20;
+const TICK_DELTA: f64 = 0.25; // second
+
+fn build_systems() -> HashMap<Uuid, System> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..SYSTEM_COUNT)
+        .map(|_| {
+            let mut system = System::new(
+                Vector3::new(rng.gen(), rng.gen(), rng.gen()),
+                Vector3::new(rng.gen(), rng.gen(), rng.gen()),
+                CenterType::from(rng.gen_range(0..5)),
+            );
+            for _ in 0..BODIES_PER_SYSTEM {
+                let mut body = Body::new(
+                    BodyType::Comet,
+                    Vector3::new(rng.gen(), rng.gen(), rng.gen()),
+                );
+                body.velocity = Vector3::new(rng.gen(), rng.gen(), rng.gen());
+                system.add_body(body).unwrap();
+            }
+            (Uuid::new_v4(), system)
+        })
+        .collect()
+}
+
+fn run(thread_count: usize, systems: &mut HashMap<Uuid, System>) -> Duration {
+    let thread_pool = ThreadPoolBuilder::new()
+        .num_threads(thread_count)
+        .build()
+        .unwrap();
+    let uuids: HashSet<Uuid> = systems.keys().copied().collect();
+    let start_time = systems
+        .values()
+        .map(|system| system.simulated_time)
+        .fold(0., f64::max);
+
+    let start = Instant::now();
+    for tick in 1..=TICKS {
+        update_systems(
+            &thread_pool,
+            systems,
+            &uuids,
+            start_time + tick as f64 * TICK_DELTA,
+        )
+        .unwrap();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let max_threads = std::thread::available_parallelism().map_or(1, |count| count.get());
+    let mut systems = build_systems();
+
+    println!("{SYSTEM_COUNT} systems, {BODIES_PER_SYSTEM} bodies each, {TICKS} ticks");
+    println!("threads\ttime\t\tspeedup");
+
+    let mut thread_counts = vec![1];
+    while thread_counts.last().unwrap() * 2 <= max_threads {
+        thread_counts.push(thread_counts.last().unwrap() * 2);
+    }
+    if *thread_counts.last().unwrap() != max_threads {
+        thread_counts.push(max_threads);
+    }
+
+    let mut single_thread_time = None;
+    for thread_count in thread_counts {
+        let elapsed = run(thread_count, &mut systems);
+        let reference = *single_thread_time.get_or_insert(elapsed);
+        println!(
+            "{thread_count}\t{elapsed:?}\t{:.2}x",
+            reference.as_secs_f64() / elapsed.as_secs_f64()
+        );
+    }
+}
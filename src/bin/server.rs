@@ -1,10 +1,17 @@
+use std::path::Path;
+
 use space_build::Galaxy;
 use space_build::GameServer;
 use space_build::Result;
+use space_build::ServerConfig;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let (_tx, mut game_server) = GameServer::new(Galaxy::new("space_build")?);
+    let config = match std::env::args().nth(1) {
+        Some(path) => ServerConfig::load(Path::new(&path))?,
+        None => ServerConfig::default(),
+    };
+    let (_tx, mut game_server) = GameServer::new(Galaxy::with_config("space_build", &config)?);
     game_server.run().await?;
     Ok(())
 }
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use serde::Serialize;
+use space_build::world::event::EventStats;
+use space_build::world::galaxy::GalaxyExport;
+use space_build::world::item::ItemId;
+use space_build::world::system::SystemStats;
+use space_build::Galaxy;
+use space_build::ServerConfig;
+
+const USAGE: &str = "usage: simulate <hours> [--db <name> | --import <file>] [--step <seconds>] [--out <file>] [--stats <file>]";
+
+#[derive(Serialize)]
+struct SimulationStats {
+    systems: Vec<SystemStats>,
+    player_items: BTreeMap<ItemId, u32>, // in the players' inventories
+    events: BTreeMap<String, u64>,       // published during the run, per kind
+}
+
+struct Args {
+    hours: f64,
+    db_name: String,
+    import: Option<PathBuf>,
+    step: f64, // second
+    out: PathBuf,
+    stats: PathBuf,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let hours = args
+        .next()
+        .and_then(|hours| hours.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!(USAGE))?;
+    let mut parsed = Args {
+        hours,
+        db_name: "space_build".to_string(),
+        import: None,
+        step: 1.,
+        out: PathBuf::from("simulation_state.json"),
+        stats: PathBuf::from("simulation_stats.json"),
+    };
+
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow::anyhow!(USAGE))?;
+        match flag.as_str() {
+            "--db" => parsed.db_name = value,
+            "--import" => parsed.import = Some(PathBuf::from(value)),
+            "--step" => parsed.step = value.parse()?,
+            "--out" => parsed.out = PathBuf::from(value),
+            "--stats" => parsed.stats = PathBuf::from(value),
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    if parsed.hours < 0. || parsed.step <= 0. {
+        anyhow::bail!(USAGE);
+    }
+    Ok(parsed)
+}
+
+fn main() -> anyhow::Result<()> {
+    let subscriber = tracing_subscriber::fmt().compact().finish();
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    let args = parse_args()?;
+
+    // The run never touches the store: the galaxy is offline once loaded,
+    // and migrated systems are not saved back.
+    let config = ServerConfig::default();
+    let mut galaxy = match &args.import {
+        Some(path) => {
+            let mut galaxy = Galaxy::offline(&config)?;
+            galaxy.import(GalaxyExport::load(path)?);
+            galaxy
+        }
+        None => {
+            let mut galaxy = Galaxy::with_config(&args.db_name, &config)?;
+            galaxy.read_only = true;
+            galaxy.load_all()?;
+            galaxy.load_systems()?;
+            galaxy.load_players()?;
+            galaxy.disconnect();
+            galaxy
+        }
+    };
+    let events = EventStats::new();
+    galaxy.events.subscribe(Box::new(events.clone()));
+    tracing::info!(
+        "Simulating {} systems and {} players for {}h",
+        galaxy.systems.len(),
+        galaxy.players.len(),
+        args.hours
+    );
+
+    let start = Instant::now();
+    galaxy.simulate(args.hours * 3600., args.step)?;
+    tracing::info!("Simulation done in {:?}", start.elapsed());
+
+    galaxy.export().save(&args.out)?;
+
+    let mut systems: Vec<SystemStats> = galaxy
+        .systems
+        .iter()
+        .map(|(uuid, system)| system.stats(*uuid))
+        .collect();
+    systems.sort_by_key(|stats| stats.uuid);
+    let mut player_items: BTreeMap<ItemId, u32> = BTreeMap::new();
+    for stack in galaxy
+        .players
+        .values()
+        .flat_map(|player| player.inventory.stacks())
+    {
+        *player_items.entry(stack.item.clone()).or_default() += stack.quantity;
+    }
+    let stats = SimulationStats {
+        systems,
+        player_items,
+        events: events.counts(),
+    };
+    std::fs::write(&args.stats, serde_json::to_string_pretty(&stats)?)?;
+
+    tracing::info!(
+        "State written to {}, statistics to {}",
+        args.out.display(),
+        args.stats.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SystemCacheConfig {
+    pub max_loaded_systems: usize,
+    pub idle_timeout: f64, // second
+}
+
+impl Default for SystemCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_loaded_systems: 1000,
+            idle_timeout: 300.,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub system_cache: SystemCacheConfig,
+    pub item_catalog: Option<PathBuf>,
+    pub recipe_catalog: Option<PathBuf>,
+}
+
+impl ServerConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::ConfigReadError)?;
+        serde_json::from_str(&json).map_err(Error::ConfigDeserializationError)
+    }
+}
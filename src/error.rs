@@ -5,7 +5,7 @@ use tokio_tungstenite::tungstenite;
 
 #[derive(Debug)]
 pub enum Error {
-    WebSocketError(tungstenite::Error),
+    WebSocketError(Box<tungstenite::Error>),
     NothingToRead,
     PlayerDeserializationError(serde_json::Error),
     ClientMessageDeserializeError(serde_json::Error),
@@ -25,6 +25,80 @@ pub enum Error {
     UnexpectedNonLoginMessage,
     SaveSystemsSetError(RedisError),
     SaveSystemsSerializationError(serde_json::Error),
+    ClientMessageSerializationError(serde_json::Error),
+    NoSystemForUuid,
+    PlayerAlreadyInTransit,
+    HyperjumpToCurrentSystem,
+    HyperjumpOutOfRange,
+    NoRouteFound,
+    NoBodyForUuid,
+    InvalidBodyParent,
+    DuplicateBodyUuid,
+    OfflineGalaxy,
+    ThreadPoolBuildError(rayon::ThreadPoolBuildError),
+    SystemDeserializationError(serde_json::Error),
+    ConfigReadError(std::io::Error),
+    ConfigDeserializationError(serde_json::Error),
+    InvalidTimeScale,
+    ClockSerializationError(serde_json::Error),
+    ClockDeserializationError(serde_json::Error),
+    SaveClockSetError(RedisError),
+    ExportReadError(std::io::Error),
+    ExportWriteError(std::io::Error),
+    ExportSerializationError(serde_json::Error),
+    ExportDeserializationError(serde_json::Error),
+    NoStructureForUuid,
+    PlayerInTransit,
+    NotStructureOwner,
+    OutOfBuildRange,
+    BlockPositionOccupied,
+    SpawnPositionOccupied,
+    BlockNotAdjacent,
+    StructureWouldSplit,
+    NoBlockAtPosition,
+    NotEnoughBlocks,
+    ChunkDecodingError,
+    SaveChunkError(RedisError),
+    LoadChunkError(RedisError),
+    InvalidBlueprintName,
+    EmptyBlueprint,
+    BlueprintTooLarge,
+    BlueprintNotConnected,
+    BlueprintCostTooLarge,
+    NoBlueprintForName,
+    BlueprintSerializationError(serde_json::Error),
+    BlueprintDeserializationError(serde_json::Error),
+    SaveBlueprintError(RedisError),
+    LoadBlueprintError(RedisError),
+    BlueprintReadError(std::io::Error),
+    BlueprintWriteError(std::io::Error),
+    NotAStation,
+    OutOfDockingRange,
+    AlreadyDocked,
+    NotDocked,
+    PlayerDocked,
+    NotMinable,
+    OutOfMiningRange,
+    MiningCooldown,
+    SimulationPaused,
+    DepositDepleted,
+    InvalidItemCatalog(String),
+    ItemCatalogReadError(std::io::Error),
+    ItemCatalogDeserializationError(serde_json::Error),
+    UnknownItem(String),
+    InventoryFull,
+    NotEnoughItems,
+    OutOfTransferRange,
+    CargoWouldNotFit,
+    InvalidRecipeCatalog(String),
+    RecipeCatalogReadError(std::io::Error),
+    RecipeCatalogDeserializationError(serde_json::Error),
+    UnknownRecipe(String),
+    NotAProductionFacility,
+    MissingProductionBlocks,
+    NoProductionRuns,
+    ProductionQueueFull,
+    NothingToCollect,
 }
 
 impl std::error::Error for Error {}
@@ -32,9 +106,9 @@ impl std::error::Error for Error {}
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::WebSocketError(error) => f.write_str(
-                format!("error with a player web socket: {}", error.to_string()).as_str(),
-            ),
+            Error::WebSocketError(error) => {
+                f.write_str(format!("error with a player web socket: {error}").as_str())
+            }
             Error::NothingToRead => f.write_str("read returned None"),
             Error::PlayerDeserializationError(json_err) => {
                 f.write_str(format!("error while deserializing a player: {json_err}").as_str())
@@ -45,9 +119,9 @@ impl Display for Error {
             Error::TickInfoSerializationError(json_err) => {
                 f.write_str(format!("error while serializing a tick info: {json_err}").as_str())
             }
-            Error::NormalClose => f.write_str(format!("client closed gracefuly").as_str()),
+            Error::NormalClose => f.write_str("client closed gracefuly"),
             Error::UnexpectedNonTextMessageError => {
-                f.write_str(format!("received non text or close web socket message").as_str())
+                f.write_str("received non text or close web socket message")
             }
             Error::RedisOpenError => f.write_str("error when opening Redis"),
             Error::RedisGetConnError(err) => {
@@ -71,6 +145,153 @@ impl Display for Error {
                 format!("error while trying to serialize systems before saving them: {redis_err}")
                     .as_str(),
             ),
+            Error::ClientMessageSerializationError(json_err) => f.write_str(
+                format!("error while serializing a client message: {json_err}").as_str(),
+            ),
+            Error::NoSystemForUuid => f.write_str("system not found for uuid"),
+            Error::PlayerAlreadyInTransit => f.write_str("player is already in hyperjump transit"),
+            Error::HyperjumpToCurrentSystem => {
+                f.write_str("hyperjump target is the player's current system")
+            }
+            Error::HyperjumpOutOfRange => f.write_str("hyperjump target is out of jump range"),
+            Error::NoRouteFound => f.write_str("no jump route between the two systems"),
+            Error::NoBodyForUuid => f.write_str("body not found for uuid"),
+            Error::InvalidBodyParent => f.write_str("body type can't orbit the given parent"),
+            Error::DuplicateBodyUuid => f.write_str("a body with this uuid already exists"),
+            Error::OfflineGalaxy => f.write_str("galaxy has no Redis connection"),
+            Error::ThreadPoolBuildError(err) => f.write_str(
+                format!("error while building the simulation thread pool: {err}").as_str(),
+            ),
+            Error::SystemDeserializationError(json_err) => {
+                f.write_str(format!("error while deserializing a system: {json_err}").as_str())
+            }
+            Error::ConfigReadError(io_err) => {
+                f.write_str(format!("error while reading the config file: {io_err}").as_str())
+            }
+            Error::ConfigDeserializationError(json_err) => f.write_str(
+                format!("error while deserializing the config file: {json_err}").as_str(),
+            ),
+            Error::InvalidTimeScale => f.write_str("time scale is out of the allowed range"),
+            Error::ClockSerializationError(json_err) => f.write_str(
+                format!("error while serializing the simulation clock: {json_err}").as_str(),
+            ),
+            Error::ClockDeserializationError(json_err) => f.write_str(
+                format!("error while deserializing the simulation clock: {json_err}").as_str(),
+            ),
+            Error::SaveClockSetError(redis_err) => f.write_str(
+                format!("error while trying to save the simulation clock in Redis: {redis_err}")
+                    .as_str(),
+            ),
+            Error::ExportReadError(io_err) => {
+                f.write_str(format!("error while reading a galaxy export: {io_err}").as_str())
+            }
+            Error::ExportWriteError(io_err) => {
+                f.write_str(format!("error while writing a galaxy export: {io_err}").as_str())
+            }
+            Error::ExportSerializationError(json_err) => {
+                f.write_str(format!("error while serializing a galaxy export: {json_err}").as_str())
+            }
+            Error::ExportDeserializationError(json_err) => f.write_str(
+                format!("error while deserializing a galaxy export: {json_err}").as_str(),
+            ),
+            Error::NoStructureForUuid => f.write_str("structure not found for uuid"),
+            Error::PlayerInTransit => f.write_str("player is in hyperjump transit"),
+            Error::NotStructureOwner => f.write_str("structure is owned by someone else"),
+            Error::OutOfBuildRange => f.write_str("block is out of build range"),
+            Error::BlockPositionOccupied => f.write_str("there is already a block there"),
+            Error::SpawnPositionOccupied => {
+                f.write_str("the structure would overlap something already there")
+            }
+            Error::BlockNotAdjacent => f.write_str("block doesn't touch the structure"),
+            Error::StructureWouldSplit => {
+                f.write_str("removing the block would split the structure")
+            }
+            Error::NoBlockAtPosition => f.write_str("there is no block there"),
+            Error::NotEnoughBlocks => f.write_str("player doesn't have the block to place"),
+            Error::ChunkDecodingError => f.write_str("structure chunk data is corrupted"),
+            Error::SaveChunkError(redis_err) => f.write_str(
+                format!("error while trying to save a structure chunk in Redis: {redis_err}")
+                    .as_str(),
+            ),
+            Error::LoadChunkError(redis_err) => f.write_str(
+                format!("error while trying to load structure chunks from Redis: {redis_err}")
+                    .as_str(),
+            ),
+            Error::InvalidBlueprintName => {
+                f.write_str("blueprint names are 1 to 32 letters, digits, dashes or underscores")
+            }
+            Error::EmptyBlueprint => f.write_str("can't make a blueprint of an empty structure"),
+            Error::BlueprintTooLarge => f.write_str("blueprint has too many blocks"),
+            Error::BlueprintNotConnected => f.write_str("blueprint blocks aren't in one piece"),
+            Error::BlueprintCostTooLarge => {
+                f.write_str("blueprint blocks wouldn't fit in a player's cargo")
+            }
+            Error::NoBlueprintForName => f.write_str("blueprint not found for name"),
+            Error::BlueprintSerializationError(json_err) => {
+                f.write_str(format!("error while serializing a blueprint: {json_err}").as_str())
+            }
+            Error::BlueprintDeserializationError(json_err) => {
+                f.write_str(format!("error while deserializing a blueprint: {json_err}").as_str())
+            }
+            Error::SaveBlueprintError(redis_err) => f.write_str(
+                format!("error while trying to save a blueprint in Redis: {redis_err}").as_str(),
+            ),
+            Error::LoadBlueprintError(redis_err) => f.write_str(
+                format!("error while trying to load blueprints from Redis: {redis_err}").as_str(),
+            ),
+            Error::BlueprintReadError(io_err) => {
+                f.write_str(format!("error while reading a blueprint file: {io_err}").as_str())
+            }
+            Error::BlueprintWriteError(io_err) => {
+                f.write_str(format!("error while writing a blueprint file: {io_err}").as_str())
+            }
+            Error::NotAStation => f.write_str("body is not a station"),
+            Error::OutOfDockingRange => f.write_str("station is out of docking range"),
+            Error::AlreadyDocked => f.write_str("player is already docked"),
+            Error::NotDocked => f.write_str("player is not docked to a station"),
+            Error::PlayerDocked => f.write_str("player must undock first"),
+            Error::NotMinable => f.write_str("body can't be mined"),
+            Error::OutOfMiningRange => f.write_str("body is out of mining range"),
+            Error::MiningCooldown => f.write_str("mining equipment is still cooling down"),
+            Error::SimulationPaused => f.write_str("the simulation is paused"),
+            Error::DepositDepleted => f.write_str("no resource of this kind is left there"),
+            Error::InvalidItemCatalog(item) => {
+                f.write_str(format!("item catalog entry {item} is invalid or duplicated").as_str())
+            }
+            Error::ItemCatalogReadError(io_err) => {
+                f.write_str(format!("error while reading the item catalog: {io_err}").as_str())
+            }
+            Error::ItemCatalogDeserializationError(json_err) => f.write_str(
+                format!("error while deserializing the item catalog: {json_err}").as_str(),
+            ),
+            Error::UnknownItem(item) => {
+                f.write_str(format!("{item} is not in the item catalog").as_str())
+            }
+            Error::InventoryFull => f.write_str("inventory can't hold that much"),
+            Error::NotEnoughItems => f.write_str("not enough items in the inventory"),
+            Error::OutOfTransferRange => f.write_str("inventory is out of transfer range"),
+            Error::CargoWouldNotFit => {
+                f.write_str("the structure's cargo wouldn't fit in its cargo blocks")
+            }
+            Error::InvalidRecipeCatalog(recipe) => f.write_str(
+                format!("recipe catalog entry {recipe} is invalid or duplicated").as_str(),
+            ),
+            Error::RecipeCatalogReadError(io_err) => {
+                f.write_str(format!("error while reading the recipe catalog: {io_err}").as_str())
+            }
+            Error::RecipeCatalogDeserializationError(json_err) => f.write_str(
+                format!("error while deserializing the recipe catalog: {json_err}").as_str(),
+            ),
+            Error::UnknownRecipe(recipe) => {
+                f.write_str(format!("{recipe} is not in the recipe catalog").as_str())
+            }
+            Error::NotAProductionFacility => f.write_str("entity can't run production"),
+            Error::MissingProductionBlocks => {
+                f.write_str("structure has no production block for this recipe")
+            }
+            Error::NoProductionRuns => f.write_str("production order has no runs"),
+            Error::ProductionQueueFull => f.write_str("production queue is full"),
+            Error::NothingToCollect => f.write_str("no finished production to collect"),
         }
     }
 }
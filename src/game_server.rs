@@ -1,9 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use crate::world::blueprint::{Blueprint, BlueprintRef, BlueprintSummary};
+use crate::world::clock::SimulationClock;
+use crate::world::docking::StationService;
+use crate::world::event::{EventLogger, TimedEvent, WorldEvent};
+use crate::world::flight::FlightInput;
 use crate::world::galaxy::Galaxy;
-use crate::world::player::Player;
-use crate::world::system::{CenterType, System};
-use crate::world::temporal::Temporal;
+use crate::world::hazard::{self, HazardReport};
+use crate::world::hyperjump::Hyperjump;
+use crate::world::inventory::{Inventory, InventoryRef, ItemTransfer};
+use crate::world::item::ItemCatalog;
+use crate::world::mining::{MiningRequest, MiningResult};
+use crate::world::player::{Player, PLAYER_SPAWN_COORDS};
+use crate::world::production::{ProductionJob, ProductionOrder, RecipeCatalog};
+use crate::world::route::JumpRoute;
+use crate::world::structure::{BlockCoordsRepr, BlockEdit, BlockType, Structure, StructureChange};
+use crate::world::system::{CenterType, System, SystemSummary, SystemView};
 use crate::{Error, GalaxyCoordsRepr, GalaxyOffsetRepr, Result};
 #[cfg(not(feature = "no-crossterm"))]
 use crossterm::event::{Event, EventStream, KeyCode};
@@ -20,43 +32,71 @@ use tracing::Level;
 use uuid::Uuid;
 
 #[cfg(not(feature = "no-crossterm"))]
-async fn crossterm_wrapper_next(prompt: &mut String, crossterm_events: &mut EventStream) {
+async fn crossterm_wrapper_next(
+    prompt: &mut String,
+    crossterm_events: &mut EventStream,
+) -> Option<String> {
     let event = crossterm_events.next().await.unwrap().unwrap();
     tracing::trace!("=> On term event");
-    match event {
-        Event::Key(key) => match key.code {
+    if let Event::Key(key) = event {
+        match key.code {
             KeyCode::Char(c) => {
-                *prompt = format!("{}{}", prompt, c.to_string());
+                *prompt = format!("{}{}", prompt, c);
             }
             KeyCode::Enter => {
-                if prompt == "help" {
-                    println!("Commands:");
-                    println!("\tdatas\t\t(print all datas)");
-                } else if prompt == "datas" {
-                    // println!("ws_accept_futs size:\t\t{}", ws_accept_futs.len());
-                    // println!("first_read_futs size:\t\t{}", first_read_futs.len());
-                    // println!("read_futs size:\t\t\t{}", read_futs.len());
-                    // println!("writers size:\t\t\t{}", self.writers.len());
-                } else {
-                    println!("Unkown command {}", prompt);
-                }
-                *prompt = "".to_string();
+                return Some(std::mem::take(prompt));
             }
             _ => {}
-        },
-        _ => {}
+        }
     }
+    None
 }
 
 #[cfg(feature = "no-crossterm")]
-async fn crossterm_wrapper_next(_: &mut String, _: &mut String) {
+async fn crossterm_wrapper_next(_: &mut String, _: &mut String) -> Option<String> {
     tokio::time::sleep(tokio::time::Duration::from_nanos(1)).await;
+    None
 }
 
+pub const MAX_GALAXY_MAP_RADIUS: f64 = 5000.; // parsec
+pub const MAX_GALAXY_MAP_PAGE_SIZE: usize = 100;
+pub const STARTER_SHIP_OFFSET: Vector3<f32> = Vector3::new(10., 0., 0.); // meter
+
 #[derive(Clone)]
 pub enum PlayerAction {
     Login(String),
-    Move(Vector3<f32>),
+    Pilot(FlightInput),
+    Hyperjump(Uuid),
+    GalaxyMap(GalaxyMapQuery),
+    Route(RouteQuery),
+    EditBlock(Uuid, Vector3<BlockCoordsRepr>, BlockEdit),
+    Blueprint(BlueprintAction),
+    Dock(Uuid),
+    Undock,
+    UseService(StationService),
+    Mine(MiningRequest),
+    ItemCatalog,
+    QueryInventory(InventoryRef),
+    MoveItems(ItemTransfer),
+    RecipeCatalog,
+    Production(ProductionAction),
+}
+
+#[derive(Clone)]
+pub enum BlueprintAction {
+    Capture(BlueprintCapture),
+    List,
+    Delete(BlueprintRef),
+    Export(BlueprintRef),
+    Import(Box<Blueprint>),
+    Spawn(BlueprintSpawn),
+}
+
+#[derive(Clone)]
+pub enum ProductionAction {
+    Order(ProductionOrder),
+    Query(Uuid),
+    Collect(Uuid),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -64,10 +104,125 @@ pub struct Login {
     pub nickname: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GalaxyMapQuery {
+    pub center: Vector3<f64>, // parsec
+    pub radius: f64,          // parsec
+    pub page: usize,
+    pub page_size: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GalaxyMapPage {
+    pub query: GalaxyMapQuery,
+    pub total: usize,
+    pub systems: Vec<SystemSummary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RouteQuery {
+    pub from: Uuid,
+    pub to: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RouteResult {
+    pub query: RouteQuery,
+    pub route: Option<JumpRoute>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlockPlacement {
+    pub structure_uuid: Uuid,
+    pub position: Vector3<BlockCoordsRepr>,
+    pub block_type: BlockType,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlockRemoval {
+    pub structure_uuid: Uuid,
+    pub position: Vector3<BlockCoordsRepr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlueprintCapture {
+    pub structure_uuid: Uuid,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlueprintSpawn {
+    pub blueprint: BlueprintRef,
+    pub position: Vector3<f32>, // meter
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerStatus {
+    pub health: f32,
+    pub hazard: HazardReport,
+    pub docked_station_uuid: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InventoryView {
+    pub inventory_ref: InventoryRef,
+    pub inventory: Inventory,
+    pub mass: f32,   // kg
+    pub volume: f32, // meter³
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProductionView {
+    pub facility_uuid: Uuid,
+    pub jobs: Vec<ProductionJob>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum ClientMessage {
     Login(Login),
-    Move(Vector3<f32>),
+    Pilot(FlightInput),
+    Hyperjump(Uuid),
+    GalaxyMap(GalaxyMapQuery),
+    Route(RouteQuery),
+    PlaceBlock(BlockPlacement),
+    RemoveBlock(BlockRemoval),
+    ReplaceBlock(BlockPlacement),
+    CaptureBlueprint(BlueprintCapture),
+    ListBlueprints,
+    DeleteBlueprint(BlueprintRef),
+    ExportBlueprint(BlueprintRef),
+    ImportBlueprint(Box<Blueprint>),
+    SpawnBlueprint(BlueprintSpawn),
+    Dock(Uuid),
+    Undock,
+    UseService(StationService),
+    Mine(MiningRequest),
+    ItemCatalog,
+    QueryInventory(InventoryRef),
+    MoveItems(ItemTransfer),
+    RecipeCatalog,
+    OrderProduction(ProductionOrder),
+    QueryProduction(Uuid),
+    CollectProduction(Uuid),
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ServerMessage {
+    System(Box<SystemView>),
+    InTransit(Hyperjump),
+    PlayerStatus(PlayerStatus),
+    GalaxyMap(GalaxyMapPage),
+    Route(RouteResult),
+    Event(TimedEvent),
+    StructureChange(StructureChange),
+    Structures(BTreeMap<Uuid, Structure>),
+    Blueprints(Vec<BlueprintSummary>),
+    Blueprint(Box<Blueprint>),
+    Mined(MiningResult),
+    ItemCatalog(ItemCatalog),
+    Inventory(InventoryView),
+    RecipeCatalog(RecipeCatalog),
+    Production(ProductionView),
 }
 
 type WsReader = SplitStream<WebSocketStream<TcpStream>>;
@@ -76,6 +231,7 @@ type WsWriter = SplitSink<WebSocketStream<TcpStream>, Message>;
 pub struct GameServer {
     galaxy: Galaxy,
     writers: HashMap<Uuid, WsWriter>,
+    known_structures: HashMap<Uuid, (Uuid, HashSet<Uuid>)>, // per player: system, structures sent
     interrupt_receiver: Receiver<()>,
 }
 
@@ -87,6 +243,7 @@ impl GameServer {
             Self {
                 galaxy,
                 writers: HashMap::new(),
+                known_structures: HashMap::new(),
                 interrupt_receiver,
             },
         )
@@ -98,7 +255,7 @@ impl GameServer {
             return Err(Error::NothingToRead);
         };
 
-        let msg = data.map_err(|err| Error::WebSocketError(err))?;
+        let msg = data.map_err(|err| Error::WebSocketError(Box::new(err)))?;
 
         match msg {
             tungstenite::protocol::Message::Text(txt) => {
@@ -106,9 +263,7 @@ impl GameServer {
                     serde_json::from_str(&txt);
 
                 match deser_res {
-                    Ok(msg) => {
-                        return Ok(msg);
-                    }
+                    Ok(msg) => Ok(msg),
                     Err(err) => Err(Error::ClientMessageDeserializeError(err)),
                 }
             }
@@ -119,13 +274,68 @@ impl GameServer {
 
     pub async fn read(mut reader: WsReader, uuid: Uuid) -> (WsReader, Uuid, Result<PlayerAction>) {
         match Self::next_message(&mut reader).await {
-            Ok(message) => match message {
-                _ => (
-                    reader,
-                    uuid,
-                    Ok(PlayerAction::Move(Vector3::new(1., 1., 1.))),
-                ),
-            },
+            Ok(message) => {
+                let action = match message {
+                    ClientMessage::Login(login) => PlayerAction::Login(login.nickname),
+                    ClientMessage::Pilot(input) => PlayerAction::Pilot(input),
+                    ClientMessage::Hyperjump(target_system_uuid) => {
+                        PlayerAction::Hyperjump(target_system_uuid)
+                    }
+                    ClientMessage::GalaxyMap(query) => PlayerAction::GalaxyMap(query),
+                    ClientMessage::Route(query) => PlayerAction::Route(query),
+                    ClientMessage::PlaceBlock(placement) => PlayerAction::EditBlock(
+                        placement.structure_uuid,
+                        placement.position,
+                        BlockEdit::Place(placement.block_type),
+                    ),
+                    ClientMessage::RemoveBlock(removal) => PlayerAction::EditBlock(
+                        removal.structure_uuid,
+                        removal.position,
+                        BlockEdit::Remove,
+                    ),
+                    ClientMessage::ReplaceBlock(placement) => PlayerAction::EditBlock(
+                        placement.structure_uuid,
+                        placement.position,
+                        BlockEdit::Replace(placement.block_type),
+                    ),
+                    ClientMessage::CaptureBlueprint(capture) => {
+                        PlayerAction::Blueprint(BlueprintAction::Capture(capture))
+                    }
+                    ClientMessage::ListBlueprints => PlayerAction::Blueprint(BlueprintAction::List),
+                    ClientMessage::DeleteBlueprint(blueprint_ref) => {
+                        PlayerAction::Blueprint(BlueprintAction::Delete(blueprint_ref))
+                    }
+                    ClientMessage::ExportBlueprint(blueprint_ref) => {
+                        PlayerAction::Blueprint(BlueprintAction::Export(blueprint_ref))
+                    }
+                    ClientMessage::ImportBlueprint(blueprint) => {
+                        PlayerAction::Blueprint(BlueprintAction::Import(blueprint))
+                    }
+                    ClientMessage::SpawnBlueprint(spawn) => {
+                        PlayerAction::Blueprint(BlueprintAction::Spawn(spawn))
+                    }
+                    ClientMessage::Dock(station_uuid) => PlayerAction::Dock(station_uuid),
+                    ClientMessage::Undock => PlayerAction::Undock,
+                    ClientMessage::UseService(service) => PlayerAction::UseService(service),
+                    ClientMessage::Mine(request) => PlayerAction::Mine(request),
+                    ClientMessage::ItemCatalog => PlayerAction::ItemCatalog,
+                    ClientMessage::QueryInventory(inventory_ref) => {
+                        PlayerAction::QueryInventory(inventory_ref)
+                    }
+                    ClientMessage::MoveItems(transfer) => PlayerAction::MoveItems(transfer),
+                    ClientMessage::RecipeCatalog => PlayerAction::RecipeCatalog,
+                    ClientMessage::OrderProduction(order) => {
+                        PlayerAction::Production(ProductionAction::Order(order))
+                    }
+                    ClientMessage::QueryProduction(facility_uuid) => {
+                        PlayerAction::Production(ProductionAction::Query(facility_uuid))
+                    }
+                    ClientMessage::CollectProduction(facility_uuid) => {
+                        PlayerAction::Production(ProductionAction::Collect(facility_uuid))
+                    }
+                };
+                (reader, uuid, Ok(action))
+            }
             Err(err) => (reader, uuid, Err(err)),
         }
     }
@@ -146,6 +356,15 @@ impl GameServer {
     }
 
     fn handle_login(&mut self, nickname: String) -> Result<Uuid> {
+        let uuid = self.login_player(nickname.clone())?;
+        self.galaxy.publish(WorldEvent::PlayerJoined {
+            player_uuid: uuid,
+            nickname,
+        });
+        Ok(uuid)
+    }
+
+    fn login_player(&mut self, nickname: String) -> Result<Uuid> {
         tracing::debug!("{nickname} is trying to login");
 
         match self.galaxy.load_player_by_nickname(nickname.clone()) {
@@ -169,14 +388,23 @@ impl GameServer {
                     CenterType::from(rng.gen_range(0..4)),
                 );
 
+                let spawn_coords = hazard::spawn_coords(&player_system, PLAYER_SPAWN_COORDS);
                 let player_sys_uuid = self.galaxy.add_system(player_system);
-                self.galaxy.save_systems()?;
 
                 let uuid = self.galaxy.add_player(Player::new(
-                    Vector3::new(100., 100., 100.),
+                    spawn_coords,
                     nickname.clone(),
                     player_sys_uuid,
+                    &self.galaxy.catalog,
                 ));
+                if let Some(system) = self.galaxy.systems.get_mut(&player_sys_uuid) {
+                    system.spawn_structure(
+                        uuid,
+                        spawn_coords + STARTER_SHIP_OFFSET,
+                        Structure::starter_ship(),
+                    );
+                }
+                self.galaxy.save_system(&player_sys_uuid)?;
                 self.galaxy.save_players()?;
                 tracing::info!("New player '{nickname}' was added to game");
                 Ok(uuid)
@@ -192,6 +420,314 @@ impl GameServer {
         }
     }
 
+    fn handle_hyperjump(&mut self, uuid: Uuid, target_system_uuid: Uuid) {
+        match self.galaxy.start_hyperjump(uuid, target_system_uuid) {
+            Ok(duration) => tracing::info!(
+                "{}: hyperjump to {target_system_uuid} started, arrival in {duration}s",
+                self.player_name(uuid)
+            ),
+            Err(err) => tracing::warn!("{}: hyperjump refused: {err}", self.player_name(uuid)),
+        }
+    }
+
+    fn handle_edit_block(
+        &mut self,
+        uuid: Uuid,
+        structure_uuid: Uuid,
+        position: Vector3<BlockCoordsRepr>,
+        edit: BlockEdit,
+    ) -> Vec<(Uuid, ServerMessage)> {
+        let change = match self.galaxy.edit_block(uuid, structure_uuid, position, edit) {
+            Ok(change) => change,
+            Err(err) => {
+                tracing::warn!("{}: block edit refused: {err}", self.player_name(uuid));
+                return Vec::new();
+            }
+        };
+        self.players_in_system(change.system_uuid)
+            .into_iter()
+            .map(|player_uuid| (player_uuid, ServerMessage::StructureChange(change.clone())))
+            .collect()
+    }
+
+    fn handle_blueprint(&mut self, uuid: Uuid, action: BlueprintAction) -> Vec<ServerMessage> {
+        let result = match action {
+            BlueprintAction::Capture(capture) => self
+                .galaxy
+                .capture_blueprint(uuid, capture.structure_uuid, capture.name)
+                .map(|_summary| None),
+            BlueprintAction::List => Ok(None),
+            BlueprintAction::Delete(blueprint_ref) => self
+                .galaxy
+                .delete_blueprint(uuid, &blueprint_ref)
+                .map(|_count| None),
+            BlueprintAction::Export(blueprint_ref) => self
+                .galaxy
+                .load_blueprint(uuid, &blueprint_ref)
+                .map(|blueprint| Some(ServerMessage::Blueprint(Box::new(blueprint)))),
+            BlueprintAction::Import(blueprint) => self
+                .galaxy
+                .import_blueprint(uuid, *blueprint)
+                .map(|_summary| None),
+            BlueprintAction::Spawn(spawn) => self
+                .galaxy
+                .spawn_blueprint(uuid, &spawn.blueprint, spawn.position)
+                .map(|_structure_uuid| None),
+        };
+        match result {
+            Ok(Some(message)) => vec![message],
+            Ok(None) => match self.galaxy.list_blueprints(uuid) {
+                Ok(summaries) => vec![ServerMessage::Blueprints(summaries)],
+                Err(err) => {
+                    tracing::warn!(
+                        "{}: could not list blueprints: {err}",
+                        self.player_name(uuid)
+                    );
+                    Vec::new()
+                }
+            },
+            Err(err) => {
+                tracing::warn!(
+                    "{}: blueprint action refused: {err}",
+                    self.player_name(uuid)
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    fn inventory_view(&self, uuid: Uuid, inventory_ref: InventoryRef) -> Result<InventoryView> {
+        let inventory = self.galaxy.query_inventory(uuid, inventory_ref)?;
+        Ok(InventoryView {
+            inventory_ref,
+            mass: inventory.mass(&self.galaxy.catalog),
+            volume: inventory.volume(&self.galaxy.catalog),
+            inventory,
+        })
+    }
+
+    fn handle_move_items(&mut self, uuid: Uuid, transfer: ItemTransfer) -> Vec<ServerMessage> {
+        if let Err(err) = self.galaxy.move_items(uuid, transfer.clone()) {
+            tracing::warn!("{}: item transfer refused: {err}", self.player_name(uuid));
+            return Vec::new();
+        }
+        [transfer.from, transfer.to]
+            .into_iter()
+            .filter_map(|inventory_ref| self.inventory_view(uuid, inventory_ref).ok())
+            .map(ServerMessage::Inventory)
+            .collect()
+    }
+
+    fn handle_production(&mut self, uuid: Uuid, action: ProductionAction) -> Vec<ServerMessage> {
+        let (facility_uuid, result) = match action {
+            ProductionAction::Order(order) => (
+                order.facility_uuid,
+                self.galaxy.order_production(uuid, order).map(|_| true),
+            ),
+            ProductionAction::Query(facility_uuid) => (facility_uuid, Ok(false)),
+            ProductionAction::Collect(facility_uuid) => (
+                facility_uuid,
+                self.galaxy
+                    .collect_production(uuid, facility_uuid)
+                    .map(|_collected| true),
+            ),
+        };
+        let inventory_changed = match result {
+            Ok(inventory_changed) => inventory_changed,
+            Err(err) => {
+                tracing::warn!(
+                    "{}: production action refused: {err}",
+                    self.player_name(uuid)
+                );
+                return Vec::new();
+            }
+        };
+        let mut messages = Vec::new();
+        match self.galaxy.production_queue(uuid, facility_uuid) {
+            Ok(jobs) => messages.push(ServerMessage::Production(ProductionView {
+                facility_uuid,
+                jobs,
+            })),
+            Err(err) => tracing::warn!(
+                "{}: production query refused: {err}",
+                self.player_name(uuid)
+            ),
+        }
+        if inventory_changed {
+            if let Ok(view) = self.inventory_view(uuid, InventoryRef::Player) {
+                messages.push(ServerMessage::Inventory(view));
+            }
+        }
+        messages
+    }
+
+    fn handle_pilot(&mut self, uuid: Uuid, input: FlightInput) {
+        if let Err(err) = self.galaxy.set_flight_controls(uuid, input) {
+            tracing::warn!("{}: flight input refused: {err}", self.player_name(uuid));
+        }
+    }
+
+    fn players_in_system(&self, system_uuid: Uuid) -> Vec<Uuid> {
+        self.galaxy
+            .players
+            .iter()
+            .filter(|(_uuid, player)| {
+                !player.is_in_transit() && player.current_system_uuid == system_uuid
+            })
+            .map(|(uuid, _player)| *uuid)
+            .collect()
+    }
+
+    pub fn galaxy_map_page(&self, mut query: GalaxyMapQuery) -> GalaxyMapPage {
+        query.radius = query.radius.clamp(0., MAX_GALAXY_MAP_RADIUS);
+        query.page_size = query.page_size.clamp(1, MAX_GALAXY_MAP_PAGE_SIZE);
+
+        let found = self
+            .galaxy
+            .system_index
+            .within_radius(&query.center, query.radius);
+        let systems = found
+            .iter()
+            .skip(query.page.saturating_mul(query.page_size))
+            .take(query.page_size)
+            .map(|summary| (*summary).clone())
+            .collect();
+
+        GalaxyMapPage {
+            total: found.len(),
+            query,
+            systems,
+        }
+    }
+
+    fn tick_messages(&self, uuid: Uuid, player: &Player) -> Vec<ServerMessage> {
+        if let Some(hyperjump) = &player.hyperjump {
+            return vec![ServerMessage::InTransit(hyperjump.clone())];
+        }
+        let Some(system) = self.galaxy.systems.get(&player.current_system_uuid) else {
+            tracing::error!("Current system for played not found.");
+            return Vec::new();
+        };
+        vec![
+            ServerMessage::System(Box::new(system.view_for(uuid))),
+            ServerMessage::PlayerStatus(PlayerStatus {
+                health: player.health,
+                hazard: player.hazard,
+                docked_station_uuid: player.docked_station_uuid,
+            }),
+        ]
+    }
+
+    fn structure_messages(&mut self) -> Vec<(Uuid, ServerMessage)> {
+        let mut messages = Vec::new();
+        for (uuid, player) in &self.galaxy.players {
+            if player.hyperjump.is_some() {
+                self.known_structures.remove(uuid);
+                continue;
+            }
+            let Some(system) = self.galaxy.systems.get(&player.current_system_uuid) else {
+                continue;
+            };
+            let known = self
+                .known_structures
+                .entry(*uuid)
+                .or_insert_with(|| (player.current_system_uuid, HashSet::new()));
+            if known.0 != player.current_system_uuid {
+                *known = (player.current_system_uuid, HashSet::new());
+            }
+            let structures = &system.entities.structures;
+            known
+                .1
+                .retain(|structure_uuid| structures.contains_key(structure_uuid));
+            let new: BTreeMap<Uuid, Structure> = structures
+                .iter()
+                .filter(|(structure_uuid, _structure)| !known.1.contains(structure_uuid))
+                .map(|(structure_uuid, structure)| (*structure_uuid, structure.clone()))
+                .collect();
+            if new.is_empty() {
+                continue;
+            }
+            known.1.extend(new.keys().copied());
+            messages.push((*uuid, ServerMessage::Structures(new)));
+        }
+        messages
+    }
+
+    fn route_result(&self, uuid: Uuid, query: RouteQuery) -> RouteResult {
+        let route = match self.galaxy.find_route(query.from, query.to) {
+            Ok(route) => Some(route),
+            Err(err) => {
+                tracing::info!("{}: no route: {err}", self.player_name(uuid));
+                None
+            }
+        };
+        RouteResult { query, route }
+    }
+
+    async fn send_message(&mut self, uuid: Uuid, message: &ServerMessage) {
+        let json_str = match serde_json::to_string(message) {
+            Ok(json_str) => json_str,
+            Err(err) => {
+                tracing::error!("Could not serialize a server message: {err}");
+                return;
+            }
+        };
+        match self.writers.get_mut(&uuid) {
+            None => tracing::error!(
+                "Could not find writer for player {}",
+                self.player_name(uuid)
+            ),
+            Some(writer) => {
+                if let Err(err) = writer.feed(Message::Text(json_str)).await {
+                    tracing::warn!("writer feed error: {err}");
+                }
+            }
+        }
+    }
+
+    pub fn handle_command(&mut self, command: &str) -> Result<()> {
+        let mut words = command.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("help"), None) => {
+                println!("Commands:");
+                println!("\tdatas\t\t(print all datas)");
+                println!("\tclock\t\t(print the simulation clock)");
+                println!("\tpause\t\t(freeze the simulation)");
+                println!("\tresume\t\t(unfreeze the simulation)");
+                println!("\ttimescale <x>\t(simulate x seconds per real second)");
+            }
+            (Some("datas"), None) => {
+                tracing::info!("writers size: {}", self.writers.len());
+            }
+            (Some("clock"), None) => {
+                let SimulationClock {
+                    time,
+                    time_scale,
+                    paused,
+                } = self.galaxy.clock;
+                tracing::info!("time: {time}s, time scale: {time_scale}, paused: {paused}");
+            }
+            (Some("pause"), None) => {
+                self.galaxy.clock.pause();
+                self.galaxy.save_clock()?;
+                tracing::info!("Simulation paused");
+            }
+            (Some("resume"), None) => {
+                self.galaxy.clock.resume();
+                self.galaxy.save_clock()?;
+                tracing::info!("Simulation resumed");
+            }
+            (Some("timescale"), Some(time_scale)) => {
+                let time_scale = time_scale.parse().map_err(|_| Error::InvalidTimeScale)?;
+                self.galaxy.clock.set_time_scale(time_scale)?;
+                self.galaxy.save_clock()?;
+                tracing::info!("Simulation time scale set to {time_scale}");
+            }
+            _ => tracing::warn!("Unkown command {}", command),
+        }
+        Ok(())
+    }
+
     fn player_name(&self, uuid: Uuid) -> String {
         self.galaxy
             .players
@@ -199,14 +735,34 @@ impl GameServer {
             .map_or("<unknown>".to_string(), |value| value.nickname.clone())
     }
     fn clean_player(&mut self, uuid: Uuid) {
+        if let Err(err) = self.galaxy.save_players() {
+            tracing::warn!("{}: could not save players: {err}", self.player_name(uuid));
+        }
         self.writers.remove(&uuid);
-        self.galaxy.players.remove(&uuid);
+        self.known_structures.remove(&uuid);
+        if self.galaxy.players.remove(&uuid).is_some() {
+            self.galaxy
+                .publish(WorldEvent::PlayerLeft { player_uuid: uuid });
+        }
+    }
+
+    fn event_messages(&self) -> Vec<(Uuid, ServerMessage)> {
+        let mut messages = Vec::new();
+        for event in self.galaxy.events.last_tick() {
+            let Some(system_uuid) = event.event.system_uuid() else {
+                continue;
+            };
+            for uuid in self.players_in_system(system_uuid) {
+                messages.push((uuid, ServerMessage::Event(event.clone())));
+            }
+        }
+        messages
     }
 
     pub async fn run(&mut self) -> Result<()> {
         self.galaxy.load_all()?;
 
-        let ref_instant = tokio::time::Instant::now();
+        let mut last_tick_instant = tokio::time::Instant::now();
 
         #[cfg(not(feature = "no-crossterm"))]
         let mut crossterm_events = EventStream::new();
@@ -222,6 +778,7 @@ impl GameServer {
 
         let mut tick_delay = tokio::time::interval(std::time::Duration::from_millis(250));
         let mut write_delay = tokio::time::interval(std::time::Duration::from_millis(250));
+        let mut save_delay = tokio::time::interval(std::time::Duration::from_secs(60));
 
         let subscriber = tracing_subscriber::fmt()
             .with_timer(tracing_subscriber::fmt::time::uptime())
@@ -229,6 +786,7 @@ impl GameServer {
             .finish();
 
         tracing::subscriber::set_global_default(subscriber).map_err(|_| Error::TracingError)?;
+        self.galaxy.events.subscribe(Box::new(EventLogger));
 
         tracing::trace!("Started");
         loop {
@@ -238,7 +796,7 @@ impl GameServer {
                 // ----------------------------------------------------
                 interrupt = self.interrupt_receiver.recv() => {
                     if interrupt.is_some() {
-                        return Ok(());
+                        return self.galaxy.save_all();
                     }
                 },
                 // ----------------------------------------------------
@@ -247,39 +805,43 @@ impl GameServer {
                 _ = tick_delay.tick() => {
                     tracing::trace!("=> On game tick");
                     let now = tokio::time::Instant::now();
-                    let delta = now - ref_instant;
-                    match self.galaxy.update(delta.as_secs_f32()) {
-                        Err(err) => tracing::error!("Galaxy update error: {err}"),
-                        Ok(_) => {},
+                    let delta = now - last_tick_instant;
+                    last_tick_instant = now;
+                    if let Err(err) = self.galaxy.tick(delta.as_secs_f32()) {
+                        tracing::error!("Galaxy update error: {err}");
+                    }
+                    if let Err(err) = self.galaxy.evict_systems(now.into_std()) {
+                        tracing::error!("System eviction error: {err}");
                     }
 
-                    for (uuid, player) in self.galaxy.players.iter_mut() {
-                        match self.galaxy.systems.get(&player.current_system_uuid) {
-                            None => tracing::error!("Current system for played not found."),
-                            Some(system) => {
-                                let json_result = serde_json::to_string(system);
-                                match json_result {
-                                    Err(err) => tracing::error!("Could not serialize a system: {err}"),
-                                    Ok(json_str) => {
-                                        match self.writers.get_mut(uuid) {
-                                            None => tracing::error!("Could not find writer for player {}", player.nickname),
-                                            Some(writer) => {
-                                                match writer.feed(Message::Text(json_str)).await {
-                                                    Err(err) => tracing::warn!("writer feed error: {err}"),
-                                                    _ => {}
-                                                }
-                                            },
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                    let mut tick_messages = self.structure_messages();
+                    tick_messages.extend(self.galaxy.players.iter().flat_map(|(uuid, player)| {
+                        self.tick_messages(*uuid, player)
+                            .into_iter()
+                            .map(|message| (*uuid, message))
+                    }));
+                    tick_messages.extend(self.event_messages());
+                    for (uuid, message) in tick_messages {
+                        self.send_message(uuid, &message).await;
                     }
                 },
                 // ----------------------------------------------------
                 // ---------------ON TERM EVENT------------------------
                 // ----------------------------------------------------
-                _ = crossterm_wrapper_next(&mut prompt, &mut crossterm_events) => {},
+                Some(command) = crossterm_wrapper_next(&mut prompt, &mut crossterm_events) => {
+                    if let Err(err) = self.handle_command(&command) {
+                        tracing::warn!("Command '{command}' failed: {err}");
+                    }
+                },
+                // ----------------------------------------------------
+                // ---------------ON SAVE TIMER------------------------
+                // ----------------------------------------------------
+                _ = save_delay.tick() => {
+                    tracing::trace!("=> On save timer");
+                    if let Err(err) = self.galaxy.save_all() {
+                        tracing::error!("Galaxy save error: {err}");
+                    }
+                },
                 // ----------------------------------------------------
                 // --------------ON FLUSH TIMER------------------------
                 // ----------------------------------------------------
@@ -316,19 +878,15 @@ impl GameServer {
                     tracing::trace!("=> On first read");
                     match read_result {
                         Err(err) => tracing::warn!("{err}"),
-                        Ok(player_action) => match player_action {
-                            PlayerAction::Login(nickname) => {
-                                match self.handle_login(nickname.clone()) {
-                                    Err(err) => tracing::warn!("login failed for {nickname}: {err}"),
-                                    Ok(uuid) => {
-                                        self.writers.insert(uuid, writer);
-                                        read_futs.push(Self::read(reader, uuid));
-                                    },
-                                }
+                        Ok(player_action) => if let PlayerAction::Login(nickname) = player_action {
+                            match self.handle_login(nickname.clone()) {
+                                Err(err) => tracing::warn!("login failed for {nickname}: {err}"),
+                                Ok(uuid) => {
+                                    self.writers.insert(uuid, writer);
+                                    read_futs.push(Self::read(reader, uuid));
+                                },
                             }
-                            _ => {}
                         }
-
                     }
                 },
                 // ----------------------------------------------------
@@ -338,7 +896,88 @@ impl GameServer {
                     tracing::trace!("=> On read");
                     match read_result {
                         Ok(player_action) => match player_action {
-                            PlayerAction::Move(_velocity) => {
+                            PlayerAction::Pilot(input) => {
+                                self.handle_pilot(uuid, input);
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::Hyperjump(target_system_uuid) => {
+                                self.handle_hyperjump(uuid, target_system_uuid);
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::GalaxyMap(query) => {
+                                let page = self.galaxy_map_page(query);
+                                self.send_message(uuid, &ServerMessage::GalaxyMap(page)).await;
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::EditBlock(structure_uuid, position, edit) => {
+                                for (player_uuid, message) in self.handle_edit_block(uuid, structure_uuid, position, edit) {
+                                    self.send_message(player_uuid, &message).await;
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::Blueprint(action) => {
+                                for message in self.handle_blueprint(uuid, action) {
+                                    self.send_message(uuid, &message).await;
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::Dock(station_uuid) => {
+                                if let Err(err) = self.galaxy.dock(uuid, station_uuid) {
+                                    tracing::warn!("{}: docking refused: {err}", self.player_name(uuid));
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::Undock => {
+                                if let Err(err) = self.galaxy.undock(uuid) {
+                                    tracing::warn!("{}: undocking refused: {err}", self.player_name(uuid));
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::UseService(service) => {
+                                if let Err(err) = self.galaxy.use_station_service(uuid, service) {
+                                    tracing::warn!("{}: station service refused: {err}", self.player_name(uuid));
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::Mine(request) => {
+                                match self.galaxy.mine(uuid, request) {
+                                    Ok(result) => self.send_message(uuid, &ServerMessage::Mined(result)).await,
+                                    Err(err) => tracing::warn!("{}: mining refused: {err}", self.player_name(uuid)),
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::ItemCatalog => {
+                                let catalog = self.galaxy.catalog.clone();
+                                self.send_message(uuid, &ServerMessage::ItemCatalog(catalog)).await;
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::QueryInventory(inventory_ref) => {
+                                match self.inventory_view(uuid, inventory_ref) {
+                                    Ok(view) => self.send_message(uuid, &ServerMessage::Inventory(view)).await,
+                                    Err(err) => tracing::warn!("{}: inventory query refused: {err}", self.player_name(uuid)),
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::MoveItems(transfer) => {
+                                for message in self.handle_move_items(uuid, transfer) {
+                                    self.send_message(uuid, &message).await;
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::RecipeCatalog => {
+                                let recipes = self.galaxy.recipes.clone();
+                                self.send_message(uuid, &ServerMessage::RecipeCatalog(recipes)).await;
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::Production(action) => {
+                                for message in self.handle_production(uuid, action) {
+                                    self.send_message(uuid, &message).await;
+                                }
+                                read_futs.push(Self::read(reader, uuid));
+                            },
+                            PlayerAction::Route(query) => {
+                                let result = self.route_result(uuid, query);
+                                self.send_message(uuid, &ServerMessage::Route(result)).await;
                                 read_futs.push(Self::read(reader, uuid));
                             },
                             _ => {
@@ -350,7 +989,7 @@ impl GameServer {
                             match err {
                                 Error::NothingToRead => tracing::info!("Nothing to read"),
                                 Error::WebSocketError(err) => {
-                                    match err {
+                                    match *err {
                                         tungstenite::Error::ConnectionClosed =>
                                             tracing::info!("{}: connection closed", self.player_name(uuid)),
                                         tungstenite::Error::Capacity(err) =>
@@ -1,12 +1,15 @@
+pub mod config;
 pub mod error;
 pub mod game_server;
 pub mod player_client;
 pub mod world;
 
+pub use config::ServerConfig;
 pub use error::Error;
 pub use game_server::ClientMessage;
 pub use game_server::GameServer;
 pub use game_server::Login;
+pub use game_server::ServerMessage;
 pub use world::galaxy::Galaxy;
 pub type Result<T> = std::result::Result<T, Error>;
 pub type GalaxyCoordsRepr = i16; // parsec
@@ -22,30 +25,46 @@ mod tests_galaxy {
     use uuid::Uuid;
     use world::{
         body::{Body, BodyType},
-        player::Player,
+        environment::PlanetEnvironment,
+        hazard::HazardReport,
+        inventory::Inventory,
+        player::{Player, MAX_PLAYER_HEALTH},
+        stellar::StellarProperties,
         system::{CenterType, System},
     };
 
     use super::*;
 
     fn get_test_system_1() -> System {
-        System {
+        let mut system = System {
             coords: Vector3::new(0, 0, 1),
             offset: Vector3::new(0, 0, 2),
             center_type: CenterType::NeutronStar,
-            bodies: vec![
-                Body {
-                    coords: Vector3::new(0., 0., 3.),
-                    velocity: Vector3::new(0., 0., 4.),
-                    body_type: BodyType::Planet,
-                },
-                Body {
-                    coords: Vector3::new(0., 0., 5.),
-                    velocity: Vector3::new(0., 0., 6.),
-                    body_type: BodyType::Station,
-                },
-            ],
+            stellar_properties: StellarProperties::generate(CenterType::NeutronStar, 1),
+            ..Default::default()
+        };
+        let bodies = [
+            Body {
+                uuid: Uuid::from_str("0c6d7c3e-5a53-4c47-a8c5-6d2b1f6a0e01").unwrap(),
+                coords: Vector3::new(0., 0., 3.),
+                velocity: Vector3::new(0., 0., 4.),
+                body_type: BodyType::Planet,
+                parent_uuid: None,
+                environment: PlanetEnvironment::generate(BodyType::Planet, 1., 3., 1),
+            },
+            Body {
+                uuid: Uuid::from_str("0c6d7c3e-5a53-4c47-a8c5-6d2b1f6a0e02").unwrap(),
+                coords: Vector3::new(0., 0., 5.),
+                velocity: Vector3::new(0., 0., 6.),
+                body_type: BodyType::Station,
+                parent_uuid: None,
+                environment: None,
+            },
+        ];
+        for body in bodies {
+            system.add_body(body).unwrap();
         }
+        system
     }
 
     fn get_test_player_1() -> Player {
@@ -54,6 +73,11 @@ mod tests_galaxy {
             current_system_uuid: Uuid::from_str("f599a2ae-58a8-449f-8007-80de1ea791e9").unwrap(),
             nickname: "test_nick1".to_string(),
             own_system_uuid: Uuid::from_str("f599a2ae-58a8-449f-8007-80de1ea791e9").unwrap(),
+            hyperjump: None,
+            health: MAX_PLAYER_HEALTH,
+            inventory: Inventory::player(),
+            docked_station_uuid: None,
+            hazard: HazardReport::default(),
         }
     }
 
@@ -63,6 +87,11 @@ mod tests_galaxy {
             current_system_uuid: Uuid::from_str("e599a2ae-58a8-449f-8007-80de1ea791e9").unwrap(),
             nickname: "test_nick2".to_string(),
             own_system_uuid: Uuid::from_str("e599a2ae-58a8-449f-8007-80de1ea791e9").unwrap(),
+            hyperjump: None,
+            health: MAX_PLAYER_HEALTH,
+            inventory: Inventory::player(),
+            docked_station_uuid: None,
+            hazard: HazardReport::default(),
         }
     }
 
@@ -78,9 +107,9 @@ mod tests_galaxy {
         let key2 = "space_build_tests:test2:test3";
         let key3 = "space_build_tests:test4";
         let val = "non";
-        let _ = conn.set(key1, val)?;
-        let _ = conn.set(key2, val)?;
-        let _ = conn.set(key3, val)?;
+        let _: () = conn.set(key1, val)?;
+        let _: () = conn.set(key2, val)?;
+        let _: () = conn.set(key3, val)?;
 
         galaxy.clear_db()?;
 
@@ -104,9 +133,10 @@ mod tests_galaxy {
         assert_eq!(Vector3::new(0, 0, 2), system_ref.offset);
         assert_eq!(CenterType::NeutronStar, system_ref.center_type);
 
-        assert_eq!(2, system_ref.bodies.len());
-        let body1 = system_ref.bodies.get(0).unwrap();
-        let body2 = system_ref.bodies.get(1).unwrap();
+        assert_eq!(2, system_ref.body_count());
+        let bodies: Vec<Body> = system_ref.bodies().collect();
+        let body1 = bodies.first().unwrap();
+        let body2 = bodies.get(1).unwrap();
 
         assert_eq!(Vector3::new(0., 0., 3.), body1.coords);
         assert_eq!(Vector3::new(0., 0., 4.), body1.velocity);
@@ -135,21 +165,25 @@ mod tests_galaxy {
             conn.get(format!("space_build_tests:system:{uuid}"))?;
 
         let system_from_redis = serde_json::from_str::<System>(&system_from_redis_json)?;
-        let body1_from_redis = system_from_redis.bodies.get(0).unwrap();
-        let body2_from_redis = system_from_redis.bodies.get(1).unwrap();
+        let bodies_from_redis: Vec<Body> = system_from_redis.bodies().collect();
+        let body1_from_redis = bodies_from_redis.first().unwrap();
+        let body2_from_redis = bodies_from_redis.get(1).unwrap();
 
         let system_ref = galaxy.systems.get(&uuid).unwrap();
-        let body1 = system_ref.bodies.get(0).unwrap();
-        let body2 = system_ref.bodies.get(1).unwrap();
+        let bodies: Vec<Body> = system_ref.bodies().collect();
+        let body1 = bodies.first().unwrap();
+        let body2 = bodies.get(1).unwrap();
 
         assert_eq!(system_ref.coords, system_from_redis.coords);
         assert_eq!(system_ref.offset, system_from_redis.offset);
         assert_eq!(system_ref.center_type, system_from_redis.center_type);
 
+        assert_eq!(body1.uuid, body1_from_redis.uuid);
         assert_eq!(body1.coords, body1_from_redis.coords);
         assert_eq!(body1.velocity, body1_from_redis.velocity);
         assert_eq!(body1.body_type, body1_from_redis.body_type);
 
+        assert_eq!(body2.uuid, body2_from_redis.uuid);
         assert_eq!(body2.coords, body2_from_redis.coords);
         assert_eq!(body2.velocity, body2_from_redis.velocity);
         assert_eq!(body2.body_type, body2_from_redis.body_type);
@@ -181,19 +215,25 @@ mod tests_galaxy {
         assert_eq!(system.coords, loaded_system.coords);
         assert_eq!(system.offset, loaded_system.offset);
         assert_eq!(system.center_type, loaded_system.center_type);
+        assert_eq!(system.stellar_properties, loaded_system.stellar_properties);
 
-        let body1 = system.bodies.get(0).unwrap();
-        let body2 = system.bodies.get(1).unwrap();
+        let bodies: Vec<Body> = system.bodies().collect();
+        let body1 = bodies.first().unwrap();
+        let body2 = bodies.get(1).unwrap();
 
-        assert_eq!(2, loaded_system.bodies.len());
+        assert_eq!(2, loaded_system.body_count());
 
-        let loaded_body1 = loaded_system.bodies.get(0).unwrap();
-        let loaded_body2 = loaded_system.bodies.get(1).unwrap();
+        let loaded_bodies: Vec<Body> = loaded_system.bodies().collect();
+        let loaded_body1 = loaded_bodies.first().unwrap();
+        let loaded_body2 = loaded_bodies.get(1).unwrap();
 
+        assert_eq!(body1.uuid, loaded_body1.uuid);
+        assert_eq!(body1.environment, loaded_body1.environment);
         assert_eq!(body1.coords, loaded_body1.coords);
         assert_eq!(body1.velocity, loaded_body1.velocity);
         assert_eq!(body1.body_type, loaded_body1.body_type);
 
+        assert_eq!(body2.uuid, loaded_body2.uuid);
         assert_eq!(body2.coords, loaded_body2.coords);
         assert_eq!(body2.velocity, loaded_body2.velocity);
         assert_eq!(body2.body_type, loaded_body2.body_type);
@@ -328,24 +368,2919 @@ mod tests_galaxy {
 }
 
 #[cfg(test)]
-mod tests_gameserver {
-    use player_client::PlayerClient;
+mod tests_hyperjump {
+    use nalgebra::Vector3;
     use serial_test::serial;
+    use world::{
+        hyperjump::{Hyperjump, HYPERJUMP_SPIN_UP_DURATION},
+        player::Player,
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
 
     use super::*;
 
-    #[tokio::test]
+    #[test]
+    fn test_09_system_distance() {
+        let system1 = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let system2 = System::new(
+            Vector3::new(3, 4, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+
+        assert_eq!(5., system1.distance_to(&system2));
+        assert_eq!(5., system2.distance_to(&system1));
+    }
+
+    #[test]
     #[serial]
-    async fn test_08_all() -> anyhow::Result<()> {
-        let (tx, mut game_server) = GameServer::new(Galaxy::new("space_build_tests")?);
-        let game_thread = tokio::spawn(async move { game_server.run().await });
+    fn test_10_hyperjump_arrival() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        let origin_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        let target_uuid = galaxy.add_system(System::new(
+            Vector3::new(100, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::BlackHole,
+        ));
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            origin_uuid,
+            &galaxy.catalog,
+        ));
+
+        assert!(matches!(
+            galaxy.start_hyperjump(player_uuid, origin_uuid),
+            Err(Error::HyperjumpToCurrentSystem)
+        ));
+
+        let duration = galaxy.start_hyperjump(player_uuid, target_uuid)?;
+        assert_eq!(Hyperjump::duration_for_distance(100.), duration);
+        assert!(duration > HYPERJUMP_SPIN_UP_DURATION);
+
+        assert!(matches!(
+            galaxy.start_hyperjump(player_uuid, target_uuid),
+            Err(Error::PlayerAlreadyInTransit)
+        ));
+
+        galaxy.update(duration / 2.)?;
+        let player = galaxy.players.get(&player_uuid).unwrap();
+        assert!(player.is_in_transit());
+        assert_eq!(origin_uuid, player.current_system_uuid);
+
+        galaxy.update(duration / 2.)?;
+        let player = galaxy.players.get(&player_uuid).unwrap();
+        assert!(!player.is_in_transit());
+        assert_eq!(target_uuid, player.current_system_uuid);
+        assert_eq!(origin_uuid, player.own_system_uuid);
 
-        let mut player = PlayerClient::connect("ws://127.0.0.1:2567").await?;
-        player.login("test".to_string()).await?;
+        Ok(())
+    }
+}
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+#[cfg(test)]
+mod tests_galaxy_map {
+    use game_server::GalaxyMapQuery;
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        system::{CenterType, System},
+        system_index::SystemIndex,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_11_system_index_within_radius() {
+        let mut index = SystemIndex::new();
+        let near = System::new(
+            Vector3::new(10, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let across_sector = System::new(
+            Vector3::new(-600, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::BlackHole,
+        );
+        let far = System::new(
+            Vector3::new(5000, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::TwoStars,
+        );
+        let near_uuid = Uuid::new_v4();
+        let across_sector_uuid = Uuid::new_v4();
+        index.insert(near.summary(near_uuid));
+        index.insert(across_sector.summary(across_sector_uuid));
+        index.insert(far.summary(Uuid::new_v4()));
+
+        let found = index.within_radius(&Vector3::new(0., 0., 0.), 1000.);
+        let found: Vec<Uuid> = found.iter().map(|summary| summary.uuid).collect();
+        assert_eq!(vec![near_uuid, across_sector_uuid], found);
+
+        index.remove(&near_uuid);
+        assert_eq!(2, index.len());
+        assert_eq!(
+            1,
+            index.within_radius(&Vector3::new(0., 0., 0.), 1000.).len()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_12_galaxy_map_pagination() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+
+        for x in 0..5 {
+            galaxy.add_system(System::new(
+                Vector3::new(x * 10, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::OneStar,
+            ));
+        }
+        let (_tx, game_server) = GameServer::new(galaxy);
+
+        let mut query = GalaxyMapQuery {
+            center: Vector3::new(0., 0., 0.),
+            radius: 25.,
+            page: 0,
+            page_size: 2,
+        };
+        let page = game_server.galaxy_map_page(query.clone());
+        assert_eq!(3, page.total);
+        assert_eq!(2, page.systems.len());
+        assert_eq!(Vector3::new(0, 0, 0), page.systems[0].coords);
+        assert_eq!(Vector3::new(10, 0, 0), page.systems[1].coords);
+
+        query.page = 1;
+        let page = game_server.galaxy_map_page(query.clone());
+        assert_eq!(1, page.systems.len());
+        assert_eq!(Vector3::new(20, 0, 0), page.systems[0].coords);
+
+        query.page = 2;
+        let page = game_server.galaxy_map_page(query);
+        assert!(page.systems.is_empty());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_route {
+    use nalgebra::Vector3;
+    use uuid::Uuid;
+    use world::{
+        hyperjump::Hyperjump,
+        route::find_route,
+        system::{CenterType, System},
+        system_index::SystemIndex,
+    };
+
+    use super::*;
+
+    fn add(index: &mut SystemIndex, x: i16, y: i16) -> Uuid {
+        let uuid = Uuid::new_v4();
+        let system = System::new(
+            Vector3::new(x, y, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        index.insert(system.summary(uuid));
+        uuid
+    }
+
+    #[test]
+    fn test_13_find_route() {
+        let mut index = SystemIndex::new();
+        let start = add(&mut index, 0, 0);
+        let relay1 = add(&mut index, 90, 0);
+        let relay2 = add(&mut index, 180, 0);
+        let detour = add(&mut index, 90, 80);
+        let goal = add(&mut index, 270, 0);
+        let isolated = add(&mut index, 2000, 0);
+
+        let route = find_route(&index, start, goal, 100.).unwrap();
+        assert_eq!(vec![start, relay1, relay2, goal], route.systems);
+        assert_eq!(3, route.jump_count());
+        assert_eq!(270., route.distance);
+        assert_eq!(
+            3. * Hyperjump::duration_for_distance(90.),
+            route.total_duration
+        );
+        assert!(!route.systems.contains(&detour));
+
+        let route = find_route(&index, start, goal, 300.).unwrap();
+        assert_eq!(vec![start, goal], route.systems);
+
+        assert!(find_route(&index, start, isolated, 100.).is_none());
+    }
+}
+
+#[cfg(test)]
+mod tests_body_uuid {
+    use nalgebra::Vector3;
+    use redis::Commands;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        body::{Body, BodyType},
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_14_body_lookup_by_uuid() -> anyhow::Result<()> {
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let planet = system.add_body(Body::new(BodyType::Planet, Vector3::new(1., 0., 0.)))?;
+        let station = system.add_body(Body::new(BodyType::Station, Vector3::new(2., 0., 0.)))?;
+
+        assert_eq!(BodyType::Station, system.body(&station).unwrap().body_type);
+        system
+            .entities
+            .positions
+            .insert(planet, Vector3::new(3., 0., 0.));
+
+        let removed = system.remove_body(&planet).unwrap();
+        assert_eq!(Vector3::new(3., 0., 0.), removed.coords);
+        assert!(system.body(&planet).is_none());
+        assert_eq!(BodyType::Station, system.body(&station).unwrap().body_type);
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_15_body_uuid_migration() -> anyhow::Result<()> {
+        let system_uuid = Uuid::new_v4();
+        {
+            let mut galaxy = Galaxy::new("space_build_tests")?;
+            galaxy.clear_db()?;
+            let legacy_json = r#"{"coords":[0,0,1],"offset":[0,0,2],"center_type":"OneStar","bodies":[{"body_type":"Planet","coords":[0.0,0.0,3.0],"velocity":[0.0,0.0,0.0]}]}"#;
+            let _: () = galaxy.connection()?.set(
+                format!("space_build_tests:system:{system_uuid}"),
+                legacy_json,
+            )?;
+        }
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.load_systems()?;
+        let body = galaxy.systems[&system_uuid].bodies().next().unwrap();
+        assert!(!body.uuid.is_nil());
+        assert_eq!(Vector3::new(0., 0., 3.), body.coords);
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.load_systems()?;
+        let system = &galaxy.systems[&system_uuid];
+        assert!(system.legacy_bodies.is_empty());
+        assert_eq!(body.uuid, system.bodies().next().unwrap().uuid);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_body_hierarchy {
+    use nalgebra::Vector3;
+    use world::{
+        body::{Body, BodyType},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_16_body_hierarchy() -> anyhow::Result<()> {
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+
+        let mut moon = Body::new(BodyType::Moon, Vector3::new(0., 10., 0.));
+        moon.velocity = Vector3::new(0., 0., 1.);
+        let gas_giant = Body::new(BodyType::GasGiant, Vector3::new(1000., 0., 0.));
+        let moon = moon.with_parent(gas_giant.uuid);
+        let station = Body::new(BodyType::Station, Vector3::new(1., 0., 0.)).with_parent(moon.uuid);
+        let comet = Body::new(BodyType::Comet, Vector3::new(0., 0., 0.));
+
+        assert!(matches!(
+            system.add_body(moon.clone()),
+            Err(Error::NoBodyForUuid)
+        ));
+        let gas_giant_uuid = system.add_body(gas_giant)?;
+        let comet_uuid = system.add_body(comet)?;
+        assert!(matches!(
+            system.add_body(Body::new(BodyType::Moon, Vector3::default()).with_parent(comet_uuid)),
+            Err(Error::InvalidBodyParent)
+        ));
+        let moon_uuid = system.add_body(moon)?;
+        let station_uuid = system.add_body(station)?;
+
+        assert!(matches!(
+            system.add_body(Body {
+                uuid: comet_uuid,
+                ..Body::new(BodyType::Comet, Vector3::default())
+            }),
+            Err(Error::DuplicateBodyUuid)
+        ));
+
+        // The moon circles the gas giant and the station circles the moon,
+        // keeping their distances.
+        system.update(2.)?;
+        let moon_coords = system.body(&moon_uuid).unwrap().coords;
+        assert!((moon_coords.norm() - 10.).abs() < 1e-3);
+        assert_ne!(Vector3::new(0., 10., 0.), moon_coords);
+        let station_coords = system.body(&station_uuid).unwrap().coords;
+        assert!((station_coords.norm() - 1.).abs() < 1e-3);
+        assert_eq!(
+            Some(Vector3::new(1000., 0., 0.)),
+            system.absolute_coords(&gas_giant_uuid)
+        );
+
+        let json = serde_json::to_string(&system)?;
+        let mut system: System = serde_json::from_str(&json)?;
+        assert_eq!(
+            Some(moon_uuid),
+            system.body(&station_uuid).unwrap().parent_uuid
+        );
+        assert_eq!(
+            Some(gas_giant_uuid),
+            system.body(&moon_uuid).unwrap().parent_uuid
+        );
+
+        system.remove_body(&gas_giant_uuid);
+        assert_eq!(1, system.body_count());
+        assert!(system.body(&comet_uuid).is_some());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_stellar {
+    use nalgebra::Vector3;
+    use world::{
+        stellar::StellarProperties,
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_17_stellar_properties() {
+        let system = System::new(
+            Vector3::new(12, -3, 40),
+            Vector3::new(1000, 0, -20),
+            CenterType::TwoStars,
+        );
+        let same_system = System::new(
+            Vector3::new(12, -3, 40),
+            Vector3::new(1000, 0, -20),
+            CenterType::TwoStars,
+        );
+        assert_eq!(2, system.stellar_properties.len());
+        assert_eq!(system.stellar_properties, same_system.stellar_properties);
+        assert!(system.total_mass() > 0.);
+        let zone = system.habitable_zone().unwrap();
+        assert!(zone.inner < zone.outer);
+
+        let sun = &StellarProperties::generate(CenterType::OneStar, 3)[0];
+        assert!(sun.habitable_zone.is_some());
+        assert!(sun.temperature > 0.);
+
+        assert_eq!(
+            3,
+            StellarProperties::generate(CenterType::ThreeStars, 3).len()
+        );
+
+        let black_hole = &StellarProperties::generate(CenterType::BlackHole, 3)[0];
+        assert_eq!(0., black_hole.luminosity);
+        assert!(black_hole.habitable_zone.is_none());
+        assert!(black_hole.mass >= 5.);
+
+        let neutron_star = &StellarProperties::generate(CenterType::NeutronStar, 3)[0];
+        assert!(neutron_star.radius < 0.0001);
+        assert!(neutron_star.temperature >= 100_000.);
+    }
+
+    #[test]
+    fn test_18_stellar_properties_migration() {
+        let mut system = System::new(
+            Vector3::new(1, 2, 3),
+            Vector3::new(4, 5, 6),
+            CenterType::OneStar,
+        );
+        let generated = system.stellar_properties.clone();
+        assert!(!system.migrate_stellar_properties());
+
+        system.stellar_properties.clear();
+        assert!(system.migrate_stellar_properties());
+        assert_eq!(generated, system.stellar_properties);
+    }
+}
+
+#[cfg(test)]
+mod tests_hazard {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use world::{
+        hazard::{self, HazardLevel},
+        player::{Player, MAX_PLAYER_HEALTH, PLAYER_SPAWN_COORDS},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_19_hazard_fields() {
+        let star = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let black_hole = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::BlackHole,
+        );
+        let neutron_star = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::NeutronStar,
+        );
+
+        let close = Vector3::new(1.0e6, 0., 0.);
+        assert_eq!(HazardLevel::Safe, hazard::hazard_at(&star, &close).level);
+
+        let near_black_hole = hazard::hazard_at(&black_hole, &close);
+        let far_from_black_hole = hazard::hazard_at(&black_hole, &Vector3::new(1.0e10, 0., 0.));
+        assert_eq!(HazardLevel::Lethal, near_black_hole.level);
+        assert!(near_black_hole.tidal_stress > far_from_black_hole.tidal_stress);
+        assert!(near_black_hole.damage_per_second() > 0.);
+
+        let near_neutron_star = hazard::hazard_at(&neutron_star, &close);
+        assert!(near_neutron_star.radiation > 0.);
+        assert!(near_neutron_star.level >= HazardLevel::Danger);
+
+        for system in [&star, &black_hole, &neutron_star] {
+            let spawn = hazard::spawn_coords(system, PLAYER_SPAWN_COORDS);
+            let report = hazard::hazard_at(system, &spawn);
+            assert_eq!(HazardLevel::Safe, report.level);
+            assert_eq!(0., report.damage_per_second());
+        }
+        assert_eq!(
+            PLAYER_SPAWN_COORDS,
+            hazard::spawn_coords(&star, PLAYER_SPAWN_COORDS)
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_20_hazard_damage_and_respawn() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+
+        let home = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let black_hole = System::new(
+            Vector3::new(10, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::BlackHole,
+        );
+        let home_uuid = galaxy.add_system(home);
+        let black_hole_uuid = galaxy.add_system(black_hole);
+
+        let mut player = Player::new(
+            Vector3::new(1.0e7, 0., 0.),
+            "test_nick1".to_string(),
+            home_uuid,
+            &galaxy.catalog,
+        );
+        player.current_system_uuid = black_hole_uuid;
+        let player_uuid = galaxy.add_player(player);
+
+        galaxy.update(0.001)?;
+        let player = &galaxy.players[&player_uuid];
+        assert!(player.hazard.level > HazardLevel::Safe);
+        assert!(player.health < MAX_PLAYER_HEALTH);
+        assert_eq!(black_hole_uuid, player.current_system_uuid);
+
+        galaxy.update(1000.)?;
+        let player = &galaxy.players[&player_uuid];
+        assert_eq!(home_uuid, player.current_system_uuid);
+        assert_eq!(MAX_PLAYER_HEALTH, player.health);
+        assert_eq!(PLAYER_SPAWN_COORDS, player.coords);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_environment {
+    use nalgebra::Vector3;
+    use world::{
+        body::{Body, BodyType},
+        environment::{AtmosphereType, Biome, PlanetEnvironment, METERS_PER_AU},
+        stellar::StellarProperties,
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    fn sun_like_system() -> System {
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        system.stellar_properties = vec![StellarProperties {
+            mass: 1.,
+            luminosity: 1.,
+            temperature: 5778.,
+            radius: 1.,
+            habitable_zone: None,
+        }];
+        system
+    }
+
+    #[test]
+    fn test_21_planet_environment() -> anyhow::Result<()> {
+        let mut system = sun_like_system();
+        let one_au = Vector3::new(METERS_PER_AU as f32, 0., 0.);
+
+        let planet = system.add_body(Body::new(BodyType::Planet, one_au))?;
+        let gas_giant = system.add_body(Body::new(BodyType::GasGiant, one_au * 5.))?;
+        let moon = system.add_body(
+            Body::new(BodyType::Moon, Vector3::new(4.0e8, 0., 0.)).with_parent(gas_giant),
+        )?;
+        let station = system.add_body(Body::new(BodyType::Station, one_au))?;
+
+        let planet_environment = system.body(&planet).unwrap().environment.clone().unwrap();
+        assert!((250. ..=290.).contains(&planet_environment.temperature));
+        assert!(planet_environment.surface_gravity > 0.);
+        assert!((0. ..1.).contains(&planet_environment.resource_richness));
+
+        let gas_giant_environment = system
+            .body(&gas_giant)
+            .unwrap()
+            .environment
+            .clone()
+            .unwrap();
+        assert_eq!(Biome::GasGiant, gas_giant_environment.biome);
+        assert_eq!(AtmosphereType::Dense, gas_giant_environment.atmosphere);
+        assert!(gas_giant_environment.temperature < planet_environment.temperature);
+
+        let moon_environment = system.body(&moon).unwrap().environment.clone().unwrap();
+        assert!(moon_environment.radius < gas_giant_environment.radius);
+
+        assert!(system.body(&station).unwrap().environment.is_none());
+
+        let again = PlanetEnvironment::generate(BodyType::Planet, 1., METERS_PER_AU, 42);
+        assert_eq!(
+            again,
+            PlanetEnvironment::generate(BodyType::Planet, 1., METERS_PER_AU, 42)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_22_planet_environment_migration() -> anyhow::Result<()> {
+        let mut system = sun_like_system();
+        let planet = system.add_body(Body::new(BodyType::Planet, Vector3::new(1.0e11, 0., 0.)))?;
+        let generated = system.body(&planet).unwrap().environment.clone();
+        assert!(!system.migrate_environments());
+
+        system.entities.environments.remove(&planet);
+        assert!(system.migrate_environments());
+        assert_eq!(generated, system.body(&planet).unwrap().environment);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_dormant {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use world::{
+        body::{Body, BodyType},
+        player::Player,
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_23_dormant_system_catch_up() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let mut comet = Body::new(BodyType::Comet, Vector3::new(0., 0., 0.));
+        comet.velocity = Vector3::new(1., 0., 0.);
+        let comet_uuid = system.add_body(comet)?;
+        let observed_uuid = galaxy.add_system(system.clone());
+        let dormant_uuid = galaxy.add_system(system);
+
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            observed_uuid,
+            &galaxy.catalog,
+        ));
+
+        for _ in 0..4 {
+            galaxy.update(2.5)?;
+        }
+        assert_eq!(10., galaxy.clock.time);
+        let observed = &galaxy.systems[&observed_uuid];
+        let dormant = &galaxy.systems[&dormant_uuid];
+        assert_eq!(10., observed.body(&comet_uuid).unwrap().coords.x);
+        assert_eq!(0., dormant.body(&comet_uuid).unwrap().coords.x);
+        assert_eq!(0., dormant.simulated_time);
+
+        galaxy
+            .players
+            .get_mut(&player_uuid)
+            .unwrap()
+            .current_system_uuid = dormant_uuid;
+        galaxy.update(1.)?;
+        let dormant = &galaxy.systems[&dormant_uuid];
+        assert_eq!(11., dormant.simulated_time);
+        assert_eq!(11., dormant.body(&comet_uuid).unwrap().coords.x);
+
+        let observed = &galaxy.systems[&observed_uuid];
+        assert_eq!(10., observed.simulated_time);
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_24_simulation_time_restored_from_systems() -> anyhow::Result<()> {
+        {
+            let mut galaxy = Galaxy::new("space_build_tests")?;
+            galaxy.clear_db()?;
+            let mut system = System::new(
+                Vector3::new(0, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::OneStar,
+            );
+            system.catch_up(42.)?;
+            galaxy.add_system(system);
+            galaxy.save_systems()?;
+        }
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.load_systems()?;
+        assert_eq!(42., galaxy.clock.time);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_49_long_catch_up_keeps_orbits() -> anyhow::Result<()> {
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let gas_giant = Body::new(BodyType::GasGiant, Vector3::new(1.0e11, 0., 0.));
+        let mut moon = Body::new(BodyType::Moon, Vector3::new(4.0e8, 0., 0.));
+        moon.velocity = Vector3::new(0., 1., 0.);
+        let moon = moon.with_parent(gas_giant.uuid);
+        let mut comet = Body::new(BodyType::Comet, Vector3::new(0., 0., 0.));
+        comet.velocity = Vector3::new(1., 0., 0.);
+        system.add_body(gas_giant)?;
+        let moon_uuid = system.add_body(moon)?;
+        let comet_uuid = system.add_body(comet)?;
+
+        // A week away.
+        let week = 7. * 24. * 3600.;
+        system.catch_up(week)?;
+        assert_eq!(week, system.simulated_time);
+        let moon_coords = system.body(&moon_uuid).unwrap().coords;
+        assert!((moon_coords.norm() / 4.0e8 - 1.).abs() < 1e-4);
+        assert_ne!(Vector3::new(4.0e8, 0., 0.), moon_coords);
+        let comet_coords = system.body(&comet_uuid).unwrap().coords;
+        assert!((comet_coords.x / week as f32 - 1.).abs() < 1e-4);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_parallel_update {
+    use nalgebra::Vector3;
+    use std::collections::{HashMap, HashSet};
+    use uuid::Uuid;
+    use world::{
+        body::{Body, BodyType},
+        galaxy::update_systems,
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_51_parallel_update_matches_sequential() -> anyhow::Result<()> {
+        let mut systems = HashMap::new();
+        for x in 0..8 {
+            let mut system = System::new(
+                Vector3::new(x, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::from(x as u32 % 4),
+            );
+            let mut comet = Body::new(BodyType::Comet, Vector3::new(0., 0., x as f32));
+            comet.velocity = Vector3::new(1., x as f32, 0.);
+            system.add_body(comet)?;
+            systems.insert(Uuid::new_v4(), system);
+        }
+        let mut expected = systems.clone();
+        let mut uuids: HashSet<Uuid> = systems.keys().copied().collect();
+        let unobserved = *uuids.iter().next().unwrap();
+        uuids.remove(&unobserved);
+        for uuid in &uuids {
+            expected.get_mut(uuid).unwrap().catch_up(10.)?;
+        }
+
+        let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(4).build()?;
+        update_systems(&thread_pool, &mut systems, &uuids, 10.)?;
+        assert_eq!(expected.len(), systems.len());
+        for (uuid, system) in expected {
+            assert_eq!(
+                serde_json::to_value(&system)?,
+                serde_json::to_value(&systems[&uuid])?
+            );
+        }
+        assert_eq!(0., systems[&unobserved].simulated_time);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_system_cache {
+    use config::{ServerConfig, SystemCacheConfig};
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use std::time::{Duration, Instant};
+    use uuid::Uuid;
+    use world::{
+        player::Player,
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    fn saved_systems(count: i16) -> anyhow::Result<Vec<Uuid>> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let uuids = (0..count)
+            .map(|x| {
+                galaxy.add_system(System::new(
+                    Vector3::new(x, 0, 0),
+                    Vector3::new(0, 0, 0),
+                    CenterType::OneStar,
+                ))
+            })
+            .collect();
+        galaxy.save_systems()?;
+        Ok(uuids)
+    }
+
+    fn galaxy_with_cache(max_loaded_systems: usize, idle_timeout: f64) -> anyhow::Result<Galaxy> {
+        let config = ServerConfig {
+            system_cache: SystemCacheConfig {
+                max_loaded_systems,
+                idle_timeout,
+            },
+            ..Default::default()
+        };
+        Ok(Galaxy::with_config("space_build_tests", &config)?)
+    }
+
+    #[test]
+    #[serial]
+    fn test_25_lazy_system_loading() -> anyhow::Result<()> {
+        let uuids = saved_systems(3)?;
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.load_all()?;
+        assert_eq!(3, galaxy.system_index.len());
+        assert!(galaxy.systems.is_empty());
+
+        assert!(galaxy.load_system(&uuids[1])?);
+        assert!(!galaxy.load_system(&uuids[1])?);
+        assert_eq!(1, galaxy.systems.len());
+        assert!(matches!(
+            galaxy.load_system(&Uuid::new_v4()),
+            Err(Error::NoSystemForUuid)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_26_system_eviction() -> anyhow::Result<()> {
+        let uuids = saved_systems(3)?;
+
+        let mut galaxy = galaxy_with_cache(2, 60.)?;
+        galaxy.load_all()?;
+        for uuid in &uuids {
+            galaxy.load_system(uuid)?;
+        }
+        galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            uuids[0],
+            &galaxy.catalog,
+        ));
+
+        galaxy.systems.get_mut(&uuids[1]).unwrap().simulated_time = 7.;
+        galaxy.system_cache.mark_dirty(uuids[1]);
+
+        let now = Instant::now();
+        galaxy.system_cache.touch(uuids[1], now);
+        galaxy
+            .system_cache
+            .touch(uuids[2], now + Duration::from_secs(1));
+
+        // Updates run on simulated time and leave eviction to the server.
+        galaxy.update(1.)?;
+        assert_eq!(3, galaxy.systems.len());
+
+        // Over the bound: the least recently used unpinned system goes.
+        assert_eq!(1, galaxy.evict_systems(now + Duration::from_secs(1))?);
+        assert!(galaxy.systems.contains_key(&uuids[0]));
+        assert!(!galaxy.systems.contains_key(&uuids[1]));
+        assert!(galaxy.systems.contains_key(&uuids[2]));
+
+        // Idle for too long: every unpinned system goes.
+        let later = now + Duration::from_secs(120);
+        assert_eq!(1, galaxy.evict_systems(later)?);
+        assert_eq!(vec![&uuids[0]], galaxy.systems.keys().collect::<Vec<_>>());
+
+        // The dirty system was saved before being evicted.
+        galaxy.load_system(&uuids[1])?;
+        assert_eq!(7., galaxy.systems[&uuids[1]].simulated_time);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_27_server_config_defaults() -> anyhow::Result<()> {
+        let config: ServerConfig =
+            serde_json::from_str(r#"{"system_cache":{"max_loaded_systems":12}}"#)?;
+        assert_eq!(12, config.system_cache.max_loaded_systems);
+        assert_eq!(
+            SystemCacheConfig::default().idle_timeout,
+            config.system_cache.idle_timeout
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_clock {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use world::{
+        body::{Body, BodyType},
+        clock::SimulationClock,
+        player::Player,
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_28_clock_pause_and_time_scale() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let mut comet = Body::new(BodyType::Comet, Vector3::new(0., 0., 0.));
+        comet.velocity = Vector3::new(1., 0., 0.);
+        let comet_uuid = system.add_body(comet)?;
+        let system_uuid = galaxy.add_system(system);
+        galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+
+        galaxy.clock.set_time_scale(10.)?;
+        galaxy.tick(1.)?;
+        assert_eq!(10., galaxy.clock.time);
+        let comet = galaxy.systems[&system_uuid].body(&comet_uuid).unwrap();
+        assert_eq!(10., comet.coords.x);
+
+        galaxy.clock.pause();
+        galaxy.tick(1.)?;
+        assert_eq!(10., galaxy.clock.time);
+        let comet = galaxy.systems[&system_uuid].body(&comet_uuid).unwrap();
+        assert_eq!(10., comet.coords.x);
+
+        galaxy.clock.resume();
+        galaxy.tick(0.5)?;
+        assert_eq!(15., galaxy.clock.time);
+
+        assert!(matches!(
+            galaxy.clock.set_time_scale(-1.),
+            Err(Error::InvalidTimeScale)
+        ));
+        assert!(matches!(
+            galaxy.clock.set_time_scale(f64::NAN),
+            Err(Error::InvalidTimeScale)
+        ));
+        assert_eq!(10., galaxy.clock.time_scale);
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_29_clock_persisted() -> anyhow::Result<()> {
+        {
+            let mut galaxy = Galaxy::new("space_build_tests")?;
+            galaxy.clear_db()?;
+            galaxy.clock.set_time_scale(4.)?;
+            galaxy.clock.pause();
+            galaxy.clock.time = 1234.;
+            galaxy.save_all()?;
+        }
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.load_all()?;
+        let expected = SimulationClock {
+            time: 1234.,
+            time_scale: 4.,
+            paused: true,
+        };
+        assert_eq!(expected, galaxy.clock);
+
+        // Galaxies saved before the clock existed start at their most
+        // advanced system, even when only the index is loaded.
+        {
+            let mut galaxy = Galaxy::new("space_build_tests")?;
+            galaxy.clear_db()?;
+            let mut system = System::new(
+                Vector3::new(0, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::OneStar,
+            );
+            system.simulated_time = 500.;
+            galaxy.add_system(system);
+            galaxy.save_systems()?;
+        }
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.load_all()?;
+        assert!(galaxy.systems.is_empty());
+        assert_eq!(500., galaxy.clock.time);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_offline {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use world::{
+        body::{Body, BodyType},
+        galaxy::GalaxyExport,
+        player::Player,
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_30_offline_simulation_from_export() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("space_build_test_30.json");
+        let (comet_uuid, observed_uuid, dormant_uuid) = {
+            let mut galaxy = Galaxy::new("space_build_tests")?;
+            galaxy.clear_db()?;
+            let mut system = System::new(
+                Vector3::new(0, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::OneStar,
+            );
+            let mut comet = Body::new(BodyType::Comet, Vector3::new(0., 0., 0.));
+            comet.velocity = Vector3::new(1., 0., 0.);
+            let comet_uuid = system.add_body(comet)?;
+            let observed_uuid = galaxy.add_system(system.clone());
+            let dormant_uuid = galaxy.add_system(system);
+            galaxy.add_player(Player::new(
+                Vector3::new(0., 0., 0.),
+                "test_nick1".to_string(),
+                observed_uuid,
+                &galaxy.catalog,
+            ));
+            galaxy.clock.pause();
+            galaxy.export().save(&path)?;
+            (comet_uuid, observed_uuid, dormant_uuid)
+        };
+
+        let mut galaxy = Galaxy::offline(&ServerConfig::default())?;
+        galaxy.import(GalaxyExport::load(&path)?);
+        std::fs::remove_file(&path)?;
+        assert_eq!(2, galaxy.systems.len());
+        assert_eq!(1, galaxy.players.len());
+        assert!(matches!(galaxy.connection(), Err(Error::OfflineGalaxy)));
+
+        galaxy.simulate(3600., 60.)?;
+        assert_eq!(3600., galaxy.clock.time);
+        assert!(galaxy.clock.paused);
+        for uuid in [observed_uuid, dormant_uuid] {
+            let stats = galaxy.systems[&uuid].stats(uuid);
+            assert_eq!(3600., stats.simulated_time);
+            assert_eq!(1, stats.body_count);
+            assert_eq!(3600., stats.max_body_distance);
+            assert_eq!(1., stats.mean_body_speed);
+        }
+        let comet = galaxy.systems[&dormant_uuid].body(&comet_uuid).unwrap();
+        assert_eq!(3600., comet.coords.x);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_entity {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        body::{Body, BodyType},
+        player::{Player, MAX_PLAYER_HEALTH, PLAYER_MASS},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_31_legacy_bodies_become_entities() -> anyhow::Result<()> {
+        let planet = Body::new(BodyType::Planet, Vector3::new(1.0e11, 0., 0.));
+        let moon = Body::new(BodyType::Moon, Vector3::new(1.0e8, 0., 0.)).with_parent(planet.uuid);
+        let mut system = System {
+            legacy_bodies: vec![moon.clone(), planet.clone()],
+            ..System::new(
+                Vector3::new(0, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::OneStar,
+            )
+        };
+
+        assert!(system.migrate_legacy_bodies());
+        assert!(!system.migrate_legacy_bodies());
+        assert_eq!(2, system.entities.len());
+        assert_eq!(
+            Some(planet.uuid),
+            system.body(&moon.uuid).unwrap().parent_uuid
+        );
+        assert!(system.entities.masses[&planet.uuid] > 0.);
+
+        let json = serde_json::to_string(&system)?;
+        assert!(!json.contains("\"bodies\""));
+        let system: System = serde_json::from_str(&json)?;
+        assert_eq!(
+            Some(Vector3::new(1.001e11, 0., 0.)),
+            system.absolute_coords(&moon.uuid)
+        );
+        let spawned: Vec<Uuid> = system.entities.iter().copied().collect();
+        assert_eq!(vec![planet.uuid, moon.uuid], spawned);
+
+        // Clients get the bodies as entities.
+        let view = serde_json::to_value(system.view_for(Uuid::nil()))?;
+        assert!(view.get("bodies").is_none());
+        assert_eq!(2, view["entities"]["body_types"].as_object().unwrap().len());
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_32_players_are_entities() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+
+        let new_system = |x| {
+            System::new(
+                Vector3::new(x, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::OneStar,
+            )
+        };
+        let first_uuid = galaxy.add_system(new_system(0));
+        let second_uuid = galaxy.add_system(new_system(1));
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(1., 2., 3.),
+            "test_nick1".to_string(),
+            first_uuid,
+            &galaxy.catalog,
+        ));
+
+        galaxy.update(1.)?;
+        let entities = &galaxy.systems[&first_uuid].entities;
+        assert!(entities.players.contains(&player_uuid));
+        assert_eq!(Vector3::new(1., 2., 3.), entities.positions[&player_uuid]);
+        assert_eq!(MAX_PLAYER_HEALTH, entities.healths[&player_uuid]);
+        assert_eq!(PLAYER_MASS, entities.masses[&player_uuid]);
+        assert_eq!(player_uuid, entities.owners[&player_uuid]);
+
+        let system = galaxy.systems.get_mut(&first_uuid).unwrap();
+        system
+            .entities
+            .velocities
+            .insert(player_uuid, Vector3::new(1., 0., 0.));
+        system.entities.healths.insert(player_uuid, 50.);
+        galaxy.update(2.)?;
+        let player = &galaxy.players[&player_uuid];
+        assert_eq!(Vector3::new(3., 2., 3.), player.coords);
+        assert_eq!(50., player.health);
+
+        galaxy
+            .players
+            .get_mut(&player_uuid)
+            .unwrap()
+            .current_system_uuid = second_uuid;
+        galaxy.update(1.)?;
+        assert!(!galaxy.systems[&first_uuid].entities.contains(&player_uuid));
+        assert!(galaxy.systems[&second_uuid]
+            .entities
+            .players
+            .contains(&player_uuid));
+
+        galaxy.players.remove(&player_uuid);
+        galaxy.update(1.)?;
+        assert!(galaxy.systems[&second_uuid].entities.is_empty());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_event {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use std::sync::{Arc, Mutex};
+    use world::{
+        event::{EventStats, EventSubscriber, TimedEvent, WorldEvent},
+        player::Player,
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    struct Recorder(Arc<Mutex<Vec<TimedEvent>>>);
+
+    impl EventSubscriber for Recorder {
+        fn on_event(&mut self, galaxy: &mut Galaxy, event: &TimedEvent) -> Result<()> {
+            assert_eq!(event.tick + 1, galaxy.events.tick());
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_33_events_in_tick_order() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        galaxy
+            .events
+            .subscribe(Box::new(Recorder(recorded.clone())));
+
+        let new_system = |x| {
+            System::new(
+                Vector3::new(x, 0, 0),
+                Vector3::new(0, 0, 0),
+                CenterType::OneStar,
+            )
+        };
+        let home_uuid = galaxy.add_system(new_system(0));
+        let target_uuid = galaxy.add_system(new_system(1));
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            home_uuid,
+            &galaxy.catalog,
+        ));
+        assert!(recorded.lock().unwrap().is_empty());
+
+        galaxy.update(1.)?;
+        galaxy.start_hyperjump(player_uuid, target_uuid)?;
+        galaxy.update(1.)?;
+        for _ in 0..10 {
+            galaxy.update(1.)?;
+        }
+
+        let events: Vec<(u64, WorldEvent)> = recorded
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| (event.tick, event.event.clone()))
+            .collect();
+        let expected = vec![
+            (
+                0,
+                WorldEvent::SystemCreated {
+                    system_uuid: home_uuid,
+                },
+            ),
+            (
+                0,
+                WorldEvent::SystemCreated {
+                    system_uuid: target_uuid,
+                },
+            ),
+            (
+                0,
+                WorldEvent::EnteredSystem {
+                    player_uuid,
+                    system_uuid: home_uuid,
+                },
+            ),
+            (
+                1,
+                WorldEvent::HyperjumpStarted {
+                    player_uuid,
+                    origin_system_uuid: home_uuid,
+                    target_system_uuid: target_uuid,
+                },
+            ),
+            (
+                1,
+                WorldEvent::LeftSystem {
+                    player_uuid,
+                    system_uuid: home_uuid,
+                },
+            ),
+        ];
+        assert_eq!(expected, events[..5]);
+        assert_eq!(6, events.len());
+        assert!(matches!(
+            events[5].1,
+            WorldEvent::EnteredSystem { system_uuid, .. } if system_uuid == target_uuid
+        ));
+        assert!(events.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_47_event_stats() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let stats = EventStats::new();
+        galaxy.events.subscribe(Box::new(stats.clone()));
+
+        let system_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        assert!(stats.counts().is_empty());
+
+        galaxy.update(1.)?;
+        let counts = stats.counts();
+        assert_eq!(Some(&1), counts.get("SystemCreated"));
+        assert_eq!(Some(&1), counts.get("EnteredSystem"));
+        assert_eq!(2, counts.values().sum::<u64>());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_structure {
+    use nalgebra::Vector3;
+    use uuid::Uuid;
+    use world::{
+        structure::{BlockType, Structure},
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_34_structure_derived_properties() -> anyhow::Result<()> {
+        let mut structure = Structure::new();
+        structure.set_block(Vector3::new(0, 0, 0), Some(BlockType::Reactor));
+        structure.set_block(Vector3::new(2, 0, 0), Some(BlockType::Thruster));
+        structure.set_block(Vector3::new(1, 0, 0), Some(BlockType::Hull));
+
+        assert_eq!(800., structure.mass());
+        assert_eq!(Vector3::new(0.625, 0., 0.), structure.center_of_mass());
+        assert_eq!(50_000., structure.thrust());
+        assert_eq!(10_000., structure.power_generation());
+        assert_eq!(1_000., structure.power_consumption());
+        assert_eq!(structure.thrust(), structure.effective_thrust());
+
+        assert_eq!(
+            Some(BlockType::Reactor),
+            structure.set_block(Vector3::new(0, 0, 0), Some(BlockType::Armor))
+        );
+        assert_eq!(0., structure.power_generation());
+        assert_eq!(0., structure.effective_thrust());
+        assert_eq!(
+            Some(BlockType::Hull),
+            structure.set_block(Vector3::new(1, 0, 0), None)
+        );
+        assert_eq!(600., structure.mass());
+        assert_eq!(2, structure.len());
+
+        let json = serde_json::to_string(&structure)?;
+        let loaded: Structure = serde_json::from_str(&json)?;
+        assert_eq!(structure.mass(), loaded.mass());
+        assert_eq!(structure.center_of_mass(), loaded.center_of_mass());
+        assert_eq!(Some(BlockType::Armor), loaded.block(&Vector3::new(0, 0, 0)));
+
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let owner = Uuid::new_v4();
+        let ship =
+            system.spawn_structure(owner, Vector3::new(5., 0., 0.), Structure::starter_ship());
+        assert_eq!(
+            vec![ship],
+            system.owned_structures(&owner).collect::<Vec<_>>()
+        );
+        let mass = system.entities.masses[&ship];
+        system.set_block(&ship, Vector3::new(0, 1, 0), Some(BlockType::Cargo))?;
+        assert_eq!(
+            mass + BlockType::Cargo.mass(),
+            system.entities.masses[&ship]
+        );
+        assert!(matches!(
+            system.set_block(&owner, Vector3::new(0, 1, 0), None),
+            Err(Error::NoStructureForUuid)
+        ));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_build {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use world::{
+        player::Player,
+        structure::{BlockEdit, BlockType, Structure, StructureChange},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_35_block_edits_validated() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let system_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        let builder = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let other = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick2".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        let ship = system.spawn_structure(
+            builder,
+            Vector3::new(10., 0., 0.),
+            Structure::starter_ship(),
+        );
+        let far_ship = system.spawn_structure(
+            builder,
+            Vector3::new(1000., 0., 0.),
+            Structure::starter_ship(),
+        );
+        galaxy.update(0.1)?;
+
+        let hulls = galaxy.players[&builder].inventory.count("hull");
+        let change = galaxy.edit_block(
+            builder,
+            ship,
+            Vector3::new(2, 0, 0),
+            BlockEdit::Place(BlockType::Hull),
+        )?;
+        assert_eq!(
+            StructureChange {
+                system_uuid,
+                structure_uuid: ship,
+                position: Vector3::new(2, 0, 0),
+                block_type: Some(BlockType::Hull),
+            },
+            change
+        );
+        assert_eq!(hulls - 1, galaxy.players[&builder].inventory.count("hull"));
+
+        let refused = [
+            (
+                other,
+                ship,
+                Vector3::new(3, 0, 0),
+                BlockEdit::Place(BlockType::Hull),
+            ),
+            (
+                builder,
+                ship,
+                Vector3::new(2, 0, 0),
+                BlockEdit::Place(BlockType::Hull),
+            ),
+            (
+                builder,
+                ship,
+                Vector3::new(5, 0, 0),
+                BlockEdit::Place(BlockType::Hull),
+            ),
+            (builder, ship, Vector3::new(5, 0, 0), BlockEdit::Remove),
+            (
+                builder,
+                ship,
+                Vector3::new(3, 0, 0),
+                BlockEdit::Place(BlockType::Armor),
+            ),
+            (
+                builder,
+                far_ship,
+                Vector3::new(2, 0, 0),
+                BlockEdit::Place(BlockType::Hull),
+            ),
+            (builder, ship, Vector3::new(0, 0, 0), BlockEdit::Remove),
+        ];
+        let errors: Vec<String> = refused
+            .into_iter()
+            .map(|(player, structure, position, edit)| {
+                galaxy
+                    .edit_block(player, structure, position, edit)
+                    .unwrap_err()
+                    .to_string()
+            })
+            .collect();
+        let expected: Vec<String> = [
+            Error::NotStructureOwner,
+            Error::BlockPositionOccupied,
+            Error::BlockNotAdjacent,
+            Error::NoBlockAtPosition,
+            Error::NotEnoughBlocks,
+            Error::OutOfBuildRange,
+            Error::StructureWouldSplit,
+        ]
+        .iter()
+        .map(|err| err.to_string())
+        .collect();
+        assert_eq!(expected, errors);
+
+        galaxy.edit_block(
+            builder,
+            ship,
+            Vector3::new(2, 0, 0),
+            BlockEdit::Replace(BlockType::Cargo),
+        )?;
+        assert_eq!(hulls, galaxy.players[&builder].inventory.count("hull"));
+        galaxy.edit_block(builder, ship, Vector3::new(2, 0, 0), BlockEdit::Remove)?;
+        let structure = &galaxy.systems[&system_uuid].entities.structures[&ship];
+        assert_eq!(Structure::starter_ship().mass(), structure.mass());
+
+        // A structure losing its last block is gone.
+        let mut beacon = Structure::new();
+        beacon.set_block(Vector3::new(0, 0, 0), Some(BlockType::Hull));
+        let beacon = galaxy
+            .systems
+            .get_mut(&system_uuid)
+            .unwrap()
+            .spawn_structure(builder, Vector3::new(0., 5., 0.), beacon);
+        galaxy.edit_block(builder, beacon, Vector3::new(0, 0, 0), BlockEdit::Remove)?;
+        assert!(!galaxy.systems[&system_uuid].entities.contains(&beacon));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_structure_chunks {
+    use nalgebra::Vector3;
+    use redis::Commands;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        structure::{BlockType, Structure},
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_36_structure_chunk_persistence() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let system_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        let mut structure = Structure::new();
+        for x in -1..=20 {
+            structure.set_block(Vector3::new(x, 3, 15), Some(BlockType::Hull));
+        }
+        structure.set_block(Vector3::new(20, 3, 15), Some(BlockType::Thruster));
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        let ship = system.spawn_structure(Uuid::new_v4(), Vector3::default(), structure.clone());
+        galaxy.save_system(&system_uuid)?;
+
+        let chunk_key = |x| format!("space_build_tests:structure_chunk:{ship}:{x}:0:0");
+        let system_json: String = galaxy
+            .connection()?
+            .get(format!("space_build_tests:system:{system_uuid}"))?;
+        assert!(!system_json.contains("Thruster"));
+        let keys: Vec<String> = galaxy
+            .connection()?
+            .keys(format!("space_build_tests:structure_chunk:{ship}:*"))?;
+        assert_eq!(3, keys.len());
+        let first_chunk: Vec<u8> = galaxy.connection()?.get(chunk_key(0))?;
+        assert_eq!(16 * 3, first_chunk.len());
+
+        // Only the changed chunk is written again.
+        let _: () = galaxy.connection()?.set(chunk_key(-1), "untouched")?;
+        galaxy.systems.get_mut(&system_uuid).unwrap().set_block(
+            &ship,
+            Vector3::new(17, 3, 15),
+            Some(BlockType::Armor),
+        )?;
+        galaxy.save_system(&system_uuid)?;
+        let untouched: String = galaxy.connection()?.get(chunk_key(-1))?;
+        assert_eq!("untouched", untouched);
+        let _: () = galaxy.connection()?.set(
+            chunk_key(-1),
+            structure.encode_chunk(&Vector3::new(-1, 0, 0)),
+        )?;
+
+        let mut loaded_galaxy = Galaxy::new("space_build_tests")?;
+        loaded_galaxy.load_system(&system_uuid)?;
+        let loaded = &loaded_galaxy.systems[&system_uuid].entities.structures[&ship];
+        assert_eq!(22, loaded.len());
+        assert_eq!(
+            Some(BlockType::Armor),
+            loaded.block(&Vector3::new(17, 3, 15))
+        );
+        assert_eq!(
+            Some(BlockType::Hull),
+            loaded.block(&Vector3::new(-1, 3, 15))
+        );
+        assert_eq!(
+            Some(BlockType::Thruster),
+            loaded.block(&Vector3::new(20, 3, 15))
+        );
+
+        loaded_galaxy
+            .systems
+            .get_mut(&system_uuid)
+            .unwrap()
+            .entities
+            .despawn(&ship);
+        loaded_galaxy.save_system(&system_uuid)?;
+        let keys: Vec<String> = galaxy
+            .connection()?
+            .keys(format!("space_build_tests:structure_chunk:{ship}:*"))?;
+        assert!(keys.is_empty());
+
+        // Chunks of a failed save are written again by the next one.
+        let mut offline = Galaxy::offline(&ServerConfig::default())?;
+        let system_uuid = offline.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        let system = offline.systems.get_mut(&system_uuid).unwrap();
+        let ship = system.spawn_structure(Uuid::new_v4(), Vector3::default(), structure);
+        assert!(offline.save_system(&system_uuid).is_err());
+        let system = &offline.systems[&system_uuid];
+        assert_eq!(3, system.entities.structures[&ship].dirty_chunks().len());
+        assert!(system.entities.structure_chunks.is_empty());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_flight {
+    use nalgebra::{Matrix3, Vector3};
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        flight::{FlightControls, FlightInput},
+        player::Player,
+        structure::{BlockType, Structure},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_37_rigid_body_flight() -> anyhow::Result<()> {
+        let mut structure = Structure::new();
+        structure.set_block(Vector3::new(-1, 0, 0), Some(BlockType::Hull));
+        structure.set_block(Vector3::new(1, 0, 0), Some(BlockType::Hull));
+        let expected =
+            Matrix3::from_diagonal(&Vector3::new(200. / 6., 200. + 200. / 6., 200. + 200. / 6.));
+        assert!((structure.inertia_tensor() - expected).norm() < 1e-3);
+
+        let mut system = System::default();
+        let ship = Structure::starter_ship();
+        let mass = ship.mass();
+        let thrust = ship.effective_thrust();
+        let uuid = system.spawn_structure(Uuid::new_v4(), Vector3::default(), ship);
+
+        // Out of range inputs are clamped to full thrust.
+        system.entities.flight_controls.insert(
+            uuid,
+            FlightControls {
+                thrust: Vector3::new(0., 0., 5.),
+                torque: Vector3::default(),
+            },
+        );
+        system.update(1.)?;
+        let speed = thrust / mass;
+        assert!((system.entities.velocities[&uuid] - Vector3::new(0., 0., speed)).norm() < 1e-3);
+        assert!((system.entities.positions[&uuid].z - speed).abs() < 1e-3);
+        assert_eq!(
+            Vector3::default(),
+            system.entities.angular_velocities[&uuid]
+        );
+
+        system.entities.flight_controls.insert(
+            uuid,
+            FlightControls {
+                thrust: Vector3::default(),
+                torque: Vector3::new(0., 1., 0.),
+            },
+        );
+        system.update(0.1)?;
+        let angular_velocity = system.entities.angular_velocities[&uuid];
+        assert!(angular_velocity.y > 0.);
+        let angle = system.entities.orientations[&uuid].angle();
+        assert!((angle - angular_velocity.y * 0.1).abs() < 1e-4);
+
+        // Thrust now pushes along the rotated axis.
+        let velocity = system.entities.velocities[&uuid];
+        system.entities.flight_controls.insert(
+            uuid,
+            FlightControls {
+                thrust: Vector3::new(0., 0., 1.),
+                torque: Vector3::default(),
+            },
+        );
+        system.update(1.)?;
+        let pushed = system.entities.velocities[&uuid] - velocity;
+        assert!(pushed.x > 0.);
+        assert!((pushed.norm() - speed).abs() < 1e-3);
+
+        let json = serde_json::to_string(&system)?;
+        let loaded: System = serde_json::from_str(&json)?;
+        assert_eq!(
+            system.entities.orientations[&uuid],
+            loaded.entities.orientations[&uuid]
+        );
+        assert!(system.entities.despawn(&uuid));
+        assert!(system.entities.orientations.is_empty());
+        assert!(system.entities.flight_controls.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_48_pilot_leaving_and_catch_up() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let system_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        let pilot = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let ship = galaxy
+            .systems
+            .get_mut(&system_uuid)
+            .unwrap()
+            .spawn_structure(pilot, Vector3::new(10., 0., 0.), Structure::starter_ship());
+        galaxy.update(0.1)?;
+        let controls = FlightControls {
+            thrust: Vector3::new(0., 0., 1.),
+            torque: Vector3::default(),
+        };
+        galaxy.set_flight_controls(
+            pilot,
+            FlightInput {
+                structure_uuid: ship,
+                controls,
+            },
+        )?;
+
+        // A long catch-up is split into steps, as if the system was ticked.
+        let mut ticked = galaxy.systems[&system_uuid].clone();
+        let mut caught_up = ticked.clone();
+        for _ in 0..10 {
+            ticked.update(1.)?;
+        }
+        caught_up.catch_up(caught_up.simulated_time + 10.)?;
+        let gap = ticked.entities.positions[&ship] - caught_up.entities.positions[&ship];
+        assert!(gap.norm() < 1e-2);
+
+        galaxy.players.remove(&pilot);
+        galaxy.update(0.1)?;
+        let system = &galaxy.systems[&system_uuid];
+        assert!(system.entities.flight_controls.is_empty());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_blueprint {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        blueprint::{Blueprint, BlueprintRef},
+        player::Player,
+        structure::{BlockCoordsRepr, BlockType, Structure},
+        system::{CenterType, System},
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_38_blueprint_lifecycle() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("space_build_test_38.json");
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let system_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        let builder = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let other = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick2".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        let ship = system.spawn_structure(
+            builder,
+            Vector3::new(10., 0., 0.),
+            Structure::starter_ship(),
+        );
+        system.set_block(&ship, Vector3::new(2, 0, 0), Some(BlockType::Hull))?;
+        let mass = system.entities.structures[&ship].mass();
+
+        let scout = |version| BlueprintRef {
+            name: "scout".to_string(),
+            version,
+        };
+        assert_eq!(
+            1,
+            galaxy
+                .capture_blueprint(builder, ship, "scout".to_string())?
+                .version
+        );
+        assert_eq!(
+            2,
+            galaxy
+                .capture_blueprint(builder, ship, "scout".to_string())?
+                .version
+        );
+        assert!(matches!(
+            galaxy.capture_blueprint(builder, ship, "bad name!".to_string()),
+            Err(Error::InvalidBlueprintName)
+        ));
+        assert!(matches!(
+            galaxy.capture_blueprint(other, ship, "stolen".to_string()),
+            Err(Error::NotStructureOwner)
+        ));
+        let summaries = galaxy.list_blueprints(builder)?;
+        assert_eq!(2, summaries.len());
+        assert_eq!(6, summaries[0].block_count);
+        assert_eq!(mass, summaries[1].mass);
+        assert!(galaxy.list_blueprints(other)?.is_empty());
+
+        galaxy.load_blueprint(builder, &scout(None))?.save(&path)?;
+        let imported = galaxy.import_blueprint(other, Blueprint::load(&path)?)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(1, imported.version);
+        assert_eq!(
+            other,
+            galaxy.load_blueprint(other, &scout(None))?.owner_uuid
+        );
+
+        let hulls = galaxy.players[&builder].inventory.count("hull");
+        let copy = galaxy.spawn_blueprint(builder, &scout(Some(1)), Vector3::new(0., 20., 0.))?;
+        let system = &galaxy.systems[&system_uuid];
+        assert_eq!(mass, system.entities.structures[&copy].mass());
+        assert_eq!(Some(&builder), system.entities.owners.get(&copy));
+        assert_eq!(hulls - 3, galaxy.players[&builder].inventory.count("hull"));
+        // The starter stock has a single cockpit.
+        assert!(matches!(
+            galaxy.spawn_blueprint(builder, &scout(None), Vector3::new(0., -20., 0.)),
+            Err(Error::NotEnoughBlocks)
+        ));
+        assert!(matches!(
+            galaxy.spawn_blueprint(other, &scout(None), Vector3::new(0., 500., 0.)),
+            Err(Error::OutOfBuildRange)
+        ));
+
+        assert_eq!(1, galaxy.delete_blueprint(builder, &scout(Some(1)))?);
+        assert_eq!(2, galaxy.list_blueprints(builder)?[0].version);
+        assert_eq!(1, galaxy.delete_blueprint(builder, &scout(None))?);
+        assert!(matches!(
+            galaxy.load_blueprint(builder, &scout(None)),
+            Err(Error::NoBlueprintForName)
+        ));
+        assert_eq!(1, galaxy.list_blueprints(other)?.len());
+        // Versions of deleted blueprints are not reused.
+        assert_eq!(
+            3,
+            galaxy
+                .capture_blueprint(builder, ship, "scout".to_string())?
+                .version
+        );
+
+        let bad_name = BlueprintRef {
+            name: "../scout".to_string(),
+            version: None,
+        };
+        assert!(matches!(
+            galaxy.load_blueprint(builder, &bad_name),
+            Err(Error::InvalidBlueprintName)
+        ));
+        assert!(matches!(
+            galaxy.delete_blueprint(builder, &bad_name),
+            Err(Error::InvalidBlueprintName)
+        ));
+        assert!(matches!(
+            galaxy.spawn_blueprint(builder, &bad_name, Vector3::new(0., 20., 0.)),
+            Err(Error::InvalidBlueprintName)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_52_blueprint_validation() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let system_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        let builder = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let import = |structure| Blueprint {
+            name: "imported".to_string(),
+            version: 7,
+            owner_uuid: Uuid::new_v4(),
+            structure,
+        };
+        let hull_line = |length: BlockCoordsRepr| {
+            let mut structure = Structure::new();
+            for x in 0..length {
+                structure.set_block(Vector3::new(x, 0, 0), Some(BlockType::Hull));
+            }
+            structure
+        };
+
+        let mut split = hull_line(2);
+        split.set_block(Vector3::new(5, 0, 0), Some(BlockType::Hull));
+        assert!(matches!(
+            galaxy.import_blueprint(builder, import(split)),
+            Err(Error::BlueprintNotConnected)
+        ));
+        assert!(matches!(
+            galaxy.import_blueprint(builder, import(hull_line(5_000))),
+            Err(Error::BlueprintTooLarge)
+        ));
+        // A player carries 200 m³, a hull block takes 1 m³.
+        assert!(matches!(
+            galaxy.import_blueprint(builder, import(hull_line(201))),
+            Err(Error::BlueprintCostTooLarge)
+        ));
+        assert!(galaxy.list_blueprints(builder)?.is_empty());
+        assert_eq!(
+            1,
+            galaxy
+                .import_blueprint(builder, import(hull_line(2)))?
+                .version
+        );
+
+        // Structures aren't spawned into anything.
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        system.spawn_structure(
+            builder,
+            Vector3::new(10., 0., 0.),
+            Structure::starter_ship(),
+        );
+        galaxy.place_players();
+        let imported = BlueprintRef {
+            name: "imported".to_string(),
+            version: None,
+        };
+        let hulls = galaxy.players[&builder].inventory.count("hull");
+        for position in [Vector3::new(0., 0., 0.), Vector3::new(8., 0., 0.)] {
+            assert!(matches!(
+                galaxy.spawn_blueprint(builder, &imported, position),
+                Err(Error::SpawnPositionOccupied)
+            ));
+        }
+        assert_eq!(hulls, galaxy.players[&builder].inventory.count("hull"));
+        galaxy.spawn_blueprint(builder, &imported, Vector3::new(0., 10., 0.))?;
+        assert_eq!(hulls - 2, galaxy.players[&builder].inventory.count("hull"));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_collision {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        body::{Body, BodyType},
+        collision::{SpatialHash, COLLISION_CELL_SIZE},
+        event::WorldEvent,
+        item::ItemCatalog,
+        player::Player,
+        structure::Structure,
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_39_spatial_hash_candidate_pairs() -> anyhow::Result<()> {
+        let mut hash = SpatialHash::default();
+        let near = [Uuid::new_v4(), Uuid::new_v4()];
+        let far = Uuid::new_v4();
+        let planet = Uuid::new_v4();
+        hash.insert(near[0], &Vector3::new(10., 0., 0.), 1.);
+        // Neighbouring cells are searched too.
+        hash.insert(near[1], &Vector3::new(-10., 0., 0.), 1.);
+        hash.insert(far, &Vector3::new(5. * COLLISION_CELL_SIZE, 0., 0.), 1.);
+        hash.insert(planet, &Vector3::new(0., 1.0e7, 0.), 6.0e6);
+
+        let pairs = hash.candidate_pairs();
+        let pair = |first: Uuid, second: Uuid| (first.min(second), first.max(second));
+        assert!(pairs.contains(&pair(near[0], near[1])));
+        assert!(!pairs.contains(&pair(near[0], far)));
+        assert!(pairs.contains(&pair(planet, far)));
+        assert!(pairs.contains(&pair(planet, near[0])));
+        assert_eq!(4, pairs.len());
+
+        hash.clear();
+        assert!(hash.candidate_pairs().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_40_players_bounce_off_each_other() -> anyhow::Result<()> {
+        let mut system = System::default();
+        let player = Player::new(
+            Vector3::default(),
+            "test_nick1".to_string(),
+            Uuid::nil(),
+            ItemCatalog::builtin(),
+        );
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        system.spawn_player(first, &player);
+        system.spawn_player(second, &player);
+        system
+            .entities
+            .positions
+            .insert(second, Vector3::new(2.5, 0., 0.));
+        system
+            .entities
+            .velocities
+            .insert(first, Vector3::new(1., 0., 0.));
+        system
+            .entities
+            .velocities
+            .insert(second, Vector3::new(-1., 0., 0.));
+
+        system.update(0.5)?;
+        assert_eq!(1, system.collisions.len());
+        let positions = &system.entities.positions;
+        assert!((positions[&second].x - positions[&first].x - 2.).abs() < 1e-4);
+        assert!((system.entities.velocities[&first].x + 0.2).abs() < 1e-4);
+        assert!((system.entities.velocities[&second].x - 0.2).abs() < 1e-4);
+
+        // Moving apart, no new impact.
+        system.collisions.clear();
+        system.update(0.5)?;
+        assert!(system.collisions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_41_ship_stopped_by_station() -> anyhow::Result<()> {
+        let mut system = System::default();
+        let station = system.add_body(Body::new(BodyType::Station, Vector3::default()))?;
+        let belt = system.add_body(Body::new(
+            BodyType::AsteroidBelt,
+            Vector3::new(0., 0., -150.),
+        ))?;
+        let ship = system.spawn_structure(
+            Uuid::new_v4(),
+            Vector3::new(0., 0., -200.),
+            Structure::starter_ship(),
+        );
+        system
+            .entities
+            .velocities
+            .insert(ship, Vector3::new(0., 0., 50.));
+
+        for _ in 0..4 {
+            system.update(0.5)?;
+        }
+        // Asteroid belts are not solid.
+        assert_eq!(1, system.collisions.len());
+        let contact = &system.collisions[0];
+        assert!([contact.first_uuid, contact.second_uuid].contains(&station));
+        assert!(![contact.first_uuid, contact.second_uuid].contains(&belt));
+        assert_eq!(Vector3::default(), system.entities.positions[&station]);
+        assert!(system.entities.positions[&ship].z < -100.);
+        assert!((system.entities.velocities[&ship].z + 10.).abs() < 1e-3);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_42_structures_collide_block_by_block() -> anyhow::Result<()> {
+        let mut system = System::default();
+        let first = system.spawn_structure(
+            Uuid::new_v4(),
+            Vector3::default(),
+            Structure::starter_ship(),
+        );
+        // Bounding spheres overlap but the blocks don't.
+        let second = system.spawn_structure(
+            Uuid::new_v4(),
+            Vector3::new(1.8, 1.8, 0.),
+            Structure::starter_ship(),
+        );
+        system.update(0.1)?;
+        assert!(system.collisions.is_empty());
+        system
+            .entities
+            .positions
+            .insert(second, Vector3::new(3.5, 0., 0.));
+        system.update(0.1)?;
+        assert!(system.collisions.is_empty());
+        // Blocks sharing a face only touch.
+        system
+            .entities
+            .positions
+            .insert(second, Vector3::new(3., 0., 0.));
+        system.update(0.1)?;
+        assert!(system.collisions.is_empty());
+        assert_eq!(3., system.entities.positions[&second].x);
+        // Overlapping blocks at rest are pushed apart without an impact.
+        system
+            .entities
+            .positions
+            .insert(second, Vector3::new(2.8, 0., 0.));
+        system.update(0.1)?;
+        assert!(system.collisions.is_empty());
+        let gap = system.entities.positions[&second].x - system.entities.positions[&first].x;
+        assert!(gap > 2.8);
+        // Catching up skips collisions, the ship goes through.
+        system
+            .entities
+            .positions
+            .insert(second, Vector3::new(10., 0., 0.));
+        system
+            .entities
+            .velocities
+            .insert(second, Vector3::new(-1., 0., 0.));
+        system.catch_up(system.simulated_time + 20.)?;
+        assert!(system.collisions.is_empty());
+        assert!(system.entities.positions[&second].x < -9.);
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let system_uuid = galaxy.add_system(System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        ));
+        galaxy.add_player(Player::new(
+            Vector3::new(100., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        let first = system.spawn_structure(
+            Uuid::new_v4(),
+            Vector3::default(),
+            Structure::starter_ship(),
+        );
+        let second = system.spawn_structure(
+            Uuid::new_v4(),
+            Vector3::new(3.8, 0., 0.),
+            Structure::starter_ship(),
+        );
+        system
+            .entities
+            .velocities
+            .insert(first, Vector3::new(1., 0., 0.));
+        galaxy.update(1.)?;
+
+        let collisions: Vec<&WorldEvent> = galaxy
+            .events
+            .last_tick()
+            .iter()
+            .map(|event| &event.event)
+            .filter(|event| matches!(event, WorldEvent::Collision { .. }))
+            .collect();
+        assert_eq!(
+            vec![&WorldEvent::Collision {
+                system_uuid,
+                first_uuid: first.min(second),
+                second_uuid: first.max(second),
+            }],
+            collisions
+        );
+        let velocities = &galaxy.systems[&system_uuid].entities.velocities;
+        assert!(velocities[&second].x > 0.);
+        assert!(velocities[&first].x < velocities[&second].x);
+        Ok(())
+    }
+
+    #[test]
+    fn test_50_docked_player_not_pushed() -> anyhow::Result<()> {
+        let mut system = System::default();
+        let station = system.add_body(Body::new(BodyType::Station, Vector3::default()))?;
+        let player = Player::new(
+            Vector3::new(140., 0., 0.),
+            "test_nick1".to_string(),
+            Uuid::nil(),
+            ItemCatalog::builtin(),
+        );
+        let docked = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        system.spawn_player(docked, &player);
+        system.spawn_player(other, &player);
+        // Docked to the station, 40 meters above its surface.
+        system.entities.parents.insert(docked, station);
+        system
+            .entities
+            .positions
+            .insert(other, Vector3::new(142.2, 0., 0.));
+        system
+            .entities
+            .velocities
+            .insert(other, Vector3::new(-1., 0., 0.));
+
+        system.update(0.5)?;
+        assert_eq!(1, system.collisions.len());
+        assert_eq!(
+            Vector3::new(140., 0., 0.),
+            system.entities.positions[&docked]
+        );
+        assert_eq!(Vector3::default(), system.entities.velocities[&docked]);
+        assert!(system.entities.velocities[&other].x > 0.);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_docking {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        body::{Body, BodyType},
+        docking::StationService,
+        event::WorldEvent,
+        player::{Player, MAX_PLAYER_HEALTH},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_43_dock_follow_station_and_undock() -> anyhow::Result<()> {
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let mut station = Body::new(BodyType::Station, Vector3::new(200., 0., 0.));
+        station.velocity = Vector3::new(10., 0., 0.);
+        let station_uuid = system.add_body(station)?;
+        let asteroid_uuid =
+            system.add_body(Body::new(BodyType::Asteroid, Vector3::new(0., 2000., 0.)))?;
+        let far_station_uuid =
+            system.add_body(Body::new(BodyType::Station, Vector3::new(5000., 0., 0.)))?;
+        let system_uuid = galaxy.add_system(system);
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(60., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        galaxy.update(0.1)?;
+
+        assert!(matches!(
+            galaxy.use_station_service(player_uuid, StationService::Repair),
+            Err(Error::NotDocked)
+        ));
+        assert!(matches!(
+            galaxy.dock(player_uuid, asteroid_uuid),
+            Err(Error::NotAStation)
+        ));
+        assert!(matches!(
+            galaxy.dock(player_uuid, far_station_uuid),
+            Err(Error::OutOfDockingRange)
+        ));
+        galaxy.dock(player_uuid, station_uuid)?;
+        assert!(matches!(
+            galaxy.dock(player_uuid, station_uuid),
+            Err(Error::AlreadyDocked)
+        ));
+        assert!(matches!(
+            galaxy.start_hyperjump(player_uuid, Uuid::new_v4()),
+            Err(Error::PlayerDocked)
+        ));
+
+        galaxy
+            .systems
+            .get_mut(&system_uuid)
+            .unwrap()
+            .entities
+            .healths
+            .insert(player_uuid, 10.);
+        galaxy.system_cache.mark_clean(&system_uuid);
+        galaxy.use_station_service(player_uuid, StationService::Repair)?;
+        assert_eq!(MAX_PLAYER_HEALTH, galaxy.players[&player_uuid].health);
+        assert!(galaxy.system_cache.is_dirty(&system_uuid));
+
+        let x = galaxy.players[&player_uuid].coords.x;
+        galaxy.update(1.)?;
+        assert!(galaxy.events.last_tick().iter().any(|event| event.event
+            == WorldEvent::Docked {
+                player_uuid,
+                station_uuid,
+                system_uuid,
+            }));
+        assert!((galaxy.players[&player_uuid].coords.x - x - 10.).abs() < 1e-3);
+        assert_eq!(
+            Some(station_uuid),
+            galaxy.players[&player_uuid].docked_station_uuid
+        );
+
+        // The docked state survives a reload.
+        galaxy.save_all()?;
+        let mut reloaded = Galaxy::new("space_build_tests")?;
+        reloaded.load_all()?;
+        let player_uuid = reloaded.load_player_by_nickname("test_nick1".to_string())?;
+        reloaded.update(0.1)?;
+        assert_eq!(
+            Some(&station_uuid),
+            reloaded.systems[&system_uuid]
+                .entities
+                .parents
+                .get(&player_uuid)
+        );
+
+        reloaded.undock(player_uuid)?;
+        assert!(matches!(
+            reloaded.undock(player_uuid),
+            Err(Error::NotDocked)
+        ));
+        let x = reloaded.players[&player_uuid].coords.x;
+        reloaded.update(1.)?;
+        assert!(reloaded.players[&player_uuid].docked_station_uuid.is_none());
+        assert!((reloaded.players[&player_uuid].coords.x - x - 10.).abs() < 1e-2);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_mining {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use world::{
+        body::{Body, BodyType},
+        mining::{self, MiningRequest, ResourceType, MINING_YIELD},
+        player::Player,
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_44_mining_deposits() -> anyhow::Result<()> {
+        assert_eq!(
+            mining::generate_deposits(BodyType::Asteroid, None, 42),
+            mining::generate_deposits(BodyType::Asteroid, None, 42)
+        );
+        assert!(mining::generate_deposits(BodyType::Planet, None, 42).is_none());
+        assert!(mining::generate_deposits(BodyType::Station, None, 42).is_none());
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let asteroid_uuid = system.add_body(Body::new(BodyType::Asteroid, Vector3::default()))?;
+        let far_asteroid_uuid =
+            system.add_body(Body::new(BodyType::Asteroid, Vector3::new(5000., 0., 0.)))?;
+        let station_uuid =
+            system.add_body(Body::new(BodyType::Station, Vector3::new(0., 600., 0.)))?;
+        let system_uuid = galaxy.add_system(system);
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(550., 0., 0.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        galaxy.update(0.1)?;
+
+        let iron = |body_uuid| MiningRequest {
+            body_uuid,
+            resource: Some(ResourceType::Iron),
+        };
+        let initial = galaxy.systems[&system_uuid].entities.deposits[&asteroid_uuid][0].amount;
+        let result = galaxy.mine(player_uuid, iron(asteroid_uuid))?;
+        assert_eq!(MINING_YIELD, result.amount);
+        assert_eq!(initial - MINING_YIELD, result.remaining);
+        assert_eq!(
+            MINING_YIELD,
+            galaxy.players[&player_uuid].inventory.count("iron_ore") as f32
+        );
+        assert!(matches!(
+            galaxy.mine(player_uuid, iron(asteroid_uuid)),
+            Err(Error::MiningCooldown)
+        ));
+        // Nothing is mined while the clock is paused.
+        galaxy.clock.pause();
+        assert!(matches!(
+            galaxy.mine(player_uuid, iron(asteroid_uuid)),
+            Err(Error::SimulationPaused)
+        ));
+        galaxy.clock.resume();
+
+        galaxy.update(1.)?;
+        assert!(matches!(
+            galaxy.mine(player_uuid, iron(far_asteroid_uuid)),
+            Err(Error::OutOfMiningRange)
+        ));
+        assert!(matches!(
+            galaxy.mine(player_uuid, iron(station_uuid)),
+            Err(Error::NotMinable)
+        ));
+        galaxy
+            .systems
+            .get_mut(&system_uuid)
+            .unwrap()
+            .entities
+            .deposits
+            .get_mut(&asteroid_uuid)
+            .unwrap()[0]
+            .amount = 4.;
+        assert_eq!(4., galaxy.mine(player_uuid, iron(asteroid_uuid))?.amount);
+        galaxy.update(1.)?;
+        assert!(matches!(
+            galaxy.mine(player_uuid, iron(asteroid_uuid)),
+            Err(Error::DepositDepleted)
+        ));
+
+        // Depletion is saved and the deposit isn't generated again.
+        galaxy.save_all()?;
+        let mut reloaded = Galaxy::new("space_build_tests")?;
+        reloaded.load_system(&system_uuid)?;
+        let deposits = &reloaded.systems[&system_uuid].entities.deposits[&asteroid_uuid];
+        assert_eq!(ResourceType::Iron, deposits[0].resource);
+        assert_eq!(0., deposits[0].amount);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_inventory {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        inventory::{Inventory, InventoryRef, ItemTransfer},
+        item::ItemCatalog,
+        player::Player,
+        structure::{BlockEdit, BlockType, Structure},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_45_inventories() -> anyhow::Result<()> {
+        let catalog = ItemCatalog::builtin();
+        for block_type in [BlockType::Hull, BlockType::Cargo, BlockType::Reactor] {
+            let item = catalog.block_item(block_type)?;
+            assert_eq!(block_type.mass(), catalog.get(item).unwrap().mass);
+        }
+        let duplicated = r#"{"items": [
+            {"id": "hull", "name": "Hull", "mass": 1, "volume": 1, "max_stack": 10},
+            {"id": "hull", "name": "Hull", "mass": 1, "volume": 1, "max_stack": 10}
+        ]}"#;
+        assert!(serde_json::from_str::<ItemCatalog>(duplicated).is_err());
+        // The starter stock follows the galaxy's catalog.
+        let renamed: ItemCatalog = serde_json::from_str(
+            r#"{"items": [{"id": "hull_plate", "name": "Hull plate", "mass": 1, "volume": 1,
+            "max_stack": 100, "block": "Hull"}]}"#,
+        )?;
+        let player = Player::new(
+            Vector3::default(),
+            "test_nick1".to_string(),
+            Uuid::nil(),
+            &renamed,
+        );
+        assert_eq!(50, player.inventory.count("hull_plate"));
+        assert_eq!(1, player.inventory.stacks().len());
+
+        let mut inventory = Inventory::new(20_000., 1000.);
+        inventory.add(catalog, "hull", 150)?;
+        assert_eq!(2, inventory.stacks().len());
+        inventory.remove("hull", 60)?;
+        assert_eq!(90, inventory.count("hull"));
+        assert_eq!(1, inventory.stacks().len());
+        assert!(matches!(
+            inventory.add(catalog, "hull", 120),
+            Err(Error::InventoryFull)
+        ));
+        assert!(matches!(
+            inventory.remove("hull", 91),
+            Err(Error::NotEnoughItems)
+        ));
+        assert!(matches!(
+            inventory.add(catalog, "unobtainium", 1),
+            Err(Error::UnknownItem(_))
+        ));
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let system_uuid = galaxy.add_system(system);
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 10.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let other = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 10.),
+            "test_nick2".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        let ship =
+            system.spawn_structure(player_uuid, Vector3::default(), Structure::starter_ship());
+        let other_ship =
+            system.spawn_structure(other, Vector3::default(), Structure::starter_ship());
+        let far_ship = system.spawn_structure(
+            player_uuid,
+            Vector3::new(1000., 0., 0.),
+            Structure::starter_ship(),
+        );
+        galaxy.update(0.1)?;
+
+        let transfer = |from, to, quantity| ItemTransfer {
+            from,
+            to,
+            item: "hull".to_string(),
+            quantity,
+        };
+        let hulls = galaxy.players[&player_uuid].inventory.count("hull");
+        // The starter ship has no cargo block.
+        assert!(matches!(
+            galaxy.move_items(
+                player_uuid,
+                transfer(InventoryRef::Player, InventoryRef::Structure(ship), 1)
+            ),
+            Err(Error::InventoryFull)
+        ));
+        galaxy.edit_block(
+            player_uuid,
+            ship,
+            Vector3::new(2, 0, 0),
+            BlockEdit::Place(BlockType::Hull),
+        )?;
+        galaxy.edit_block(
+            player_uuid,
+            ship,
+            Vector3::new(3, 0, 0),
+            BlockEdit::Place(BlockType::Cargo),
+        )?;
+        galaxy.move_items(
+            player_uuid,
+            transfer(InventoryRef::Player, InventoryRef::Structure(ship), 1),
+        )?;
+        assert_eq!(
+            hulls - 2,
+            galaxy.players[&player_uuid].inventory.count("hull")
+        );
+        let cargo = galaxy.query_inventory(player_uuid, InventoryRef::Structure(ship))?;
+        assert_eq!(1, cargo.count("hull"));
+        assert_eq!(5000., cargo.max_mass);
+        // Other players don't see the cargo.
+        let system = &galaxy.systems[&system_uuid];
+        let view = system.view_for(player_uuid);
+        assert_eq!(1, view.entities.inventories[&ship].count("hull"));
+        let view = system.view_for(other);
+        assert!(!view.entities.inventories.contains_key(&ship));
+
+        let refused = [
+            (
+                transfer(InventoryRef::Player, InventoryRef::Structure(ship), 1),
+                Error::InventoryFull,
+            ),
+            (
+                transfer(InventoryRef::Structure(ship), InventoryRef::Player, 2),
+                Error::NotEnoughItems,
+            ),
+            (
+                transfer(InventoryRef::Player, InventoryRef::Structure(other_ship), 1),
+                Error::NotStructureOwner,
+            ),
+            (
+                transfer(InventoryRef::Player, InventoryRef::Structure(far_ship), 1),
+                Error::OutOfTransferRange,
+            ),
+        ];
+        for (transfer, expected) in refused {
+            assert_eq!(
+                expected.to_string(),
+                galaxy
+                    .move_items(player_uuid, transfer)
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+
+        // Cargo blocks holding items stay.
+        for edit in [BlockEdit::Remove, BlockEdit::Replace(BlockType::Hull)] {
+            assert!(matches!(
+                galaxy.edit_block(player_uuid, ship, Vector3::new(3, 0, 0), edit),
+                Err(Error::CargoWouldNotFit)
+            ));
+        }
+        assert_eq!(
+            1,
+            galaxy
+                .query_inventory(player_uuid, InventoryRef::Structure(ship))?
+                .count("hull")
+        );
+
+        galaxy.save_all()?;
+        let mut reloaded = Galaxy::new("space_build_tests")?;
+        reloaded.load_all()?;
+        reloaded.load_players()?;
+        assert_eq!(
+            hulls - 2,
+            reloaded.players[&player_uuid].inventory.count("hull")
+        );
+        reloaded.load_system(&system_uuid)?;
+        assert_eq!(
+            1,
+            reloaded
+                .query_inventory(player_uuid, InventoryRef::Structure(ship))?
+                .count("hull")
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_production {
+    use nalgebra::Vector3;
+    use serial_test::serial;
+    use uuid::Uuid;
+    use world::{
+        body::{Body, BodyType},
+        event::WorldEvent,
+        item::ItemCatalog,
+        player::Player,
+        production::{ProductionOrder, RecipeCatalog, MAX_PRODUCTION_QUEUE},
+        structure::{BlockType, Structure},
+        system::{CenterType, System},
+        temporal::Temporal,
+    };
+
+    use super::*;
+
+    #[test]
+    #[serial]
+    fn test_46_production_queues() -> anyhow::Result<()> {
+        RecipeCatalog::builtin().check_items(ItemCatalog::builtin())?;
+        let recipe = |inputs: &str, duration: f32| {
+            format!(
+                r#"{{"recipes": [{{"id": "plate", "name": "Plate", "facility": "Refinery",
+                "duration": {duration}, "inputs": [{inputs}],
+                "outputs": [{{"item": "steel_plate", "quantity": 1}}]}}]}}"#
+            )
+        };
+        let ore = r#"{"item": "iron_ore", "quantity": 10}"#;
+        assert!(serde_json::from_str::<RecipeCatalog>(&recipe(ore, 0.)).is_err());
+        let unknown: RecipeCatalog =
+            serde_json::from_str(&recipe(r#"{"item": "mithril", "quantity": 1}"#, 5.))?;
+        assert!(unknown.check_items(ItemCatalog::builtin()).is_err());
+
+        let mut galaxy = Galaxy::new("space_build_tests")?;
+        galaxy.clear_db()?;
+        let mut system = System::new(
+            Vector3::new(0, 0, 0),
+            Vector3::new(0, 0, 0),
+            CenterType::OneStar,
+        );
+        let station_uuid =
+            system.add_body(Body::new(BodyType::Station, Vector3::new(0., 0., -130.)))?;
+        let system_uuid = galaxy.add_system(system);
+        let player_uuid = galaxy.add_player(Player::new(
+            Vector3::new(0., 0., 10.),
+            "test_nick1".to_string(),
+            system_uuid,
+            &galaxy.catalog,
+        ));
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        let ship =
+            system.spawn_structure(player_uuid, Vector3::default(), Structure::starter_ship());
+        system.set_block(&ship, Vector3::new(2, 0, 0), Some(BlockType::Assembler))?;
+        let far_ship = system.spawn_structure(
+            player_uuid,
+            Vector3::new(1000., 0., 0.),
+            Structure::starter_ship(),
+        );
+        galaxy.update(0.1)?;
+        let player = galaxy.players.get_mut(&player_uuid).unwrap();
+        player
+            .inventory
+            .insert(&galaxy.catalog, "steel_plate", 100)?;
+        let hulls = player.inventory.count("hull");
+
+        let order = |facility_uuid, recipe: &str, runs| ProductionOrder {
+            facility_uuid,
+            recipe: recipe.to_string(),
+            runs,
+        };
+        galaxy.order_production(player_uuid, order(ship, "hull", 2))?;
+        assert_eq!(
+            80,
+            galaxy.players[&player_uuid].inventory.count("steel_plate")
+        );
+        let refused = [
+            (order(ship, "smelt_iron", 1), Error::MissingProductionBlocks),
+            (order(ship, "hull", 9), Error::NotEnoughItems),
+            (order(ship, "hull", 19), Error::ProductionQueueFull),
+            (order(ship, "hull", 0), Error::NoProductionRuns),
+            (order(far_ship, "hull", 1), Error::OutOfTransferRange),
+            (order(station_uuid, "hull", 1), Error::NotDocked),
+            (
+                order(ship, "warp_core", 1),
+                Error::UnknownRecipe("warp_core".to_string()),
+            ),
+        ];
+        for (order, expected) in refused {
+            assert_eq!(
+                expected.to_string(),
+                galaxy
+                    .order_production(player_uuid, order)
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+
+        // A single assembler runs one job at a time.
+        let system = galaxy.systems.get_mut(&system_uuid).unwrap();
+        system.update(15.)?;
+        assert_eq!(1, system.finished_jobs.len());
+        let queue = galaxy.production_queue(player_uuid, ship)?;
+        assert!(queue[0].is_finished());
+        assert_eq!(5., queue[1].elapsed);
+        assert_eq!(1, galaxy.collect_production(player_uuid, ship)?.len());
+        assert_eq!(
+            hulls + 1,
+            galaxy.players[&player_uuid].inventory.count("hull")
+        );
+        assert!(matches!(
+            galaxy.collect_production(player_uuid, ship),
+            Err(Error::NothingToCollect)
+        ));
+        galaxy.publish_finished_production();
+        galaxy.dispatch_events();
+        assert!(galaxy.events.last_tick().iter().any(|event| event.event
+            == WorldEvent::ProductionFinished {
+                owner_uuid: player_uuid,
+                facility_uuid: ship,
+                recipe: "hull".to_string(),
+                system_uuid,
+            }));
+
+        // Jobs of other players don't take the player's room in the queue.
+        let mut stranger_job = galaxy.production_queue(player_uuid, ship)?[0].clone();
+        stranger_job.owner_uuid = Uuid::new_v4();
+        galaxy.dock(player_uuid, station_uuid)?;
+        let station_queue = galaxy
+            .systems
+            .get_mut(&system_uuid)
+            .unwrap()
+            .entities
+            .productions
+            .entry(station_uuid)
+            .or_default();
+        station_queue.extend(vec![stranger_job.clone(); MAX_PRODUCTION_QUEUE]);
+        galaxy.order_production(player_uuid, order(station_uuid, "hull", 5))?;
+        galaxy
+            .systems
+            .get_mut(&system_uuid)
+            .unwrap()
+            .entities
+            .productions
+            .get_mut(&station_uuid)
+            .unwrap()
+            .retain(|job| job.owner_uuid == player_uuid);
+        // Other players don't see the jobs.
+        let system = &galaxy.systems[&system_uuid];
+        let view = system.view_for(player_uuid);
+        assert_eq!(5, view.entities.productions[&station_uuid].len());
+        let view = system.view_for(Uuid::new_v4());
+        assert!(view.entities.productions.is_empty());
+        assert!(view.entities.inventories.is_empty());
+
+        // Station jobs keep running while nobody is around: the system
+        // catches up in one step once loaded again.
+        galaxy.save_all()?;
+        let mut reloaded = Galaxy::new("space_build_tests")?;
+        reloaded.load_players()?;
+        reloaded.load_system(&system_uuid)?;
+        let system = reloaded.systems.get_mut(&system_uuid).unwrap();
+        system.catch_up(system.simulated_time + 20.)?;
+        let station_jobs = system
+            .finished_jobs
+            .iter()
+            .filter(|(facility_uuid, _job)| *facility_uuid == station_uuid);
+        assert_eq!(5, station_jobs.count());
+        assert!(reloaded
+            .production_queue(player_uuid, station_uuid)?
+            .iter()
+            .all(|job| job.is_finished()));
+        reloaded.collect_production(player_uuid, station_uuid)?;
+        assert_eq!(
+            hulls + 6,
+            reloaded.players[&player_uuid].inventory.count("hull")
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_gameserver {
+    use player_client::PlayerClient;
+    use serial_test::serial;
+
+    use super::*;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_08_all() -> anyhow::Result<()> {
+        let (tx, mut game_server) = GameServer::new(Galaxy::new("space_build_tests")?);
+        let game_thread = tokio::spawn(async move { game_server.run().await });
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+
+        let mut player = PlayerClient::connect("ws://127.0.0.1:2567").await?;
+        player.login("test".to_string()).await?;
+
+        // The starter ship's blocks come once, apart from the system view.
+        let mut structures = 0;
+        let mut views = 0;
+        while views < 3 {
+            let message: serde_json::Value = serde_json::from_str(&player.wait_message().await?)?;
+            if let Some(sent) = message.get("Structures") {
+                assert_eq!(0, views);
+                structures += sent.as_object().unwrap().len();
+            } else if let Some(view) = message.get("System") {
+                assert!(view.get("bodies").is_none());
+                assert!(view["entities"]["structures"]
+                    .as_object()
+                    .unwrap()
+                    .is_empty());
+                views += 1;
+            }
+        }
+        assert_eq!(1, structures);
 
         tx.send(()).await?;
 
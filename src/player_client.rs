@@ -12,6 +12,18 @@ use tokio_tungstenite::{
     MaybeTlsStream, WebSocketStream,
 };
 
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::game_server::{
+    BlockPlacement, BlockRemoval, BlueprintCapture, BlueprintSpawn, GalaxyMapQuery, RouteQuery,
+};
+use crate::world::blueprint::{Blueprint, BlueprintRef};
+use crate::world::docking::StationService;
+use crate::world::flight::FlightInput;
+use crate::world::inventory::{InventoryRef, ItemTransfer};
+use crate::world::mining::MiningRequest;
+use crate::world::production::ProductionOrder;
 use crate::{ClientMessage, Error, Login};
 
 use crate::Result;
@@ -25,24 +37,137 @@ impl PlayerClient {
     pub async fn connect(host: &str) -> Result<PlayerClient> {
         let (ws_stream, _) = connect_async(host)
             .await
-            .map_err(|err| Error::WebSocketError(err))?;
+            .map_err(|err| Error::WebSocketError(Box::new(err)))?;
 
         let (writer, reader) = ws_stream.split();
         Ok(PlayerClient { writer, reader })
     }
 
-    pub async fn login(&mut self, nickname: String) -> Result<()> {
-        let login = ClientMessage::Login(Login { nickname });
-
-        let to_send = serde_json::to_string(&login).unwrap();
+    async fn send_message(&mut self, message: &ClientMessage) -> Result<()> {
+        let to_send =
+            serde_json::to_string(message).map_err(Error::ClientMessageSerializationError)?;
 
         self.writer
             .send(tungstenite::protocol::Message::Text(to_send))
             .await
-            .unwrap();
+            .map_err(|err| Error::WebSocketError(Box::new(err)))
+    }
+
+    pub async fn login(&mut self, nickname: String) -> Result<()> {
+        self.send_message(&ClientMessage::Login(Login { nickname }))
+            .await
+    }
+
+    pub async fn hyperjump(&mut self, target_system_uuid: Uuid) -> Result<()> {
+        self.send_message(&ClientMessage::Hyperjump(target_system_uuid))
+            .await
+    }
 
-        Ok(())
+    pub async fn query_galaxy_map(&mut self, query: GalaxyMapQuery) -> Result<()> {
+        self.send_message(&ClientMessage::GalaxyMap(query)).await
     }
+
+    pub async fn query_route(&mut self, from: Uuid, to: Uuid) -> Result<()> {
+        self.send_message(&ClientMessage::Route(RouteQuery { from, to }))
+            .await
+    }
+    pub async fn place_block(&mut self, placement: BlockPlacement) -> Result<()> {
+        self.send_message(&ClientMessage::PlaceBlock(placement))
+            .await
+    }
+
+    pub async fn remove_block(&mut self, removal: BlockRemoval) -> Result<()> {
+        self.send_message(&ClientMessage::RemoveBlock(removal))
+            .await
+    }
+
+    pub async fn replace_block(&mut self, placement: BlockPlacement) -> Result<()> {
+        self.send_message(&ClientMessage::ReplaceBlock(placement))
+            .await
+    }
+
+    pub async fn pilot(&mut self, input: FlightInput) -> Result<()> {
+        self.send_message(&ClientMessage::Pilot(input)).await
+    }
+
+    pub async fn capture_blueprint(&mut self, capture: BlueprintCapture) -> Result<()> {
+        self.send_message(&ClientMessage::CaptureBlueprint(capture))
+            .await
+    }
+
+    pub async fn list_blueprints(&mut self) -> Result<()> {
+        self.send_message(&ClientMessage::ListBlueprints).await
+    }
+
+    pub async fn delete_blueprint(&mut self, blueprint_ref: BlueprintRef) -> Result<()> {
+        self.send_message(&ClientMessage::DeleteBlueprint(blueprint_ref))
+            .await
+    }
+
+    pub async fn export_blueprint(&mut self, blueprint_ref: BlueprintRef) -> Result<()> {
+        self.send_message(&ClientMessage::ExportBlueprint(blueprint_ref))
+            .await
+    }
+
+    pub async fn import_blueprint(&mut self, path: &Path) -> Result<()> {
+        let blueprint = Blueprint::load(path)?;
+        self.send_message(&ClientMessage::ImportBlueprint(Box::new(blueprint)))
+            .await
+    }
+
+    pub async fn spawn_blueprint(&mut self, spawn: BlueprintSpawn) -> Result<()> {
+        self.send_message(&ClientMessage::SpawnBlueprint(spawn))
+            .await
+    }
+
+    pub async fn dock(&mut self, station_uuid: Uuid) -> Result<()> {
+        self.send_message(&ClientMessage::Dock(station_uuid)).await
+    }
+
+    pub async fn undock(&mut self) -> Result<()> {
+        self.send_message(&ClientMessage::Undock).await
+    }
+
+    pub async fn use_service(&mut self, service: StationService) -> Result<()> {
+        self.send_message(&ClientMessage::UseService(service)).await
+    }
+
+    pub async fn mine(&mut self, request: MiningRequest) -> Result<()> {
+        self.send_message(&ClientMessage::Mine(request)).await
+    }
+
+    pub async fn item_catalog(&mut self) -> Result<()> {
+        self.send_message(&ClientMessage::ItemCatalog).await
+    }
+
+    pub async fn query_inventory(&mut self, inventory_ref: InventoryRef) -> Result<()> {
+        self.send_message(&ClientMessage::QueryInventory(inventory_ref))
+            .await
+    }
+
+    pub async fn move_items(&mut self, transfer: ItemTransfer) -> Result<()> {
+        self.send_message(&ClientMessage::MoveItems(transfer)).await
+    }
+
+    pub async fn recipe_catalog(&mut self) -> Result<()> {
+        self.send_message(&ClientMessage::RecipeCatalog).await
+    }
+
+    pub async fn order_production(&mut self, order: ProductionOrder) -> Result<()> {
+        self.send_message(&ClientMessage::OrderProduction(order))
+            .await
+    }
+
+    pub async fn query_production(&mut self, facility_uuid: Uuid) -> Result<()> {
+        self.send_message(&ClientMessage::QueryProduction(facility_uuid))
+            .await
+    }
+
+    pub async fn collect_production(&mut self, facility_uuid: Uuid) -> Result<()> {
+        self.send_message(&ClientMessage::CollectProduction(facility_uuid))
+            .await
+    }
+
     pub async fn wait_message(&mut self) -> crate::Result<String> {
         let maybe_read_result = self.reader.next().await;
 
@@ -0,0 +1,68 @@
+use super::structure::{BlockType, Structure};
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use uuid::Uuid;
+
+pub const MAX_BLUEPRINT_NAME_LENGTH: usize = 32;
+pub const MAX_BLUEPRINT_BLOCKS: usize = 4_096;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Blueprint {
+    pub name: String,
+    pub version: u32,
+    pub owner_uuid: Uuid,
+    pub structure: Structure,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct BlueprintSummary {
+    pub name: String,
+    pub version: u32,
+    pub block_count: usize,
+    pub mass: f32, // kg
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct BlueprintRef {
+    pub name: String,
+    pub version: Option<u32>,
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_BLUEPRINT_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl Blueprint {
+    pub fn summary(&self) -> BlueprintSummary {
+        BlueprintSummary {
+            name: self.name.clone(),
+            version: self.version,
+            block_count: self.structure.len(),
+            mass: self.structure.mass(),
+        }
+    }
+
+    pub fn cost(&self) -> BTreeMap<BlockType, u32> {
+        let mut cost = BTreeMap::new();
+        for (_position, block_type) in self.structure.blocks() {
+            *cost.entry(*block_type).or_default() += 1;
+        }
+        cost
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::BlueprintReadError)?;
+        serde_json::from_str(&json).map_err(Error::BlueprintDeserializationError)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(Error::BlueprintSerializationError)?;
+        std::fs::write(path, json).map_err(Error::BlueprintWriteError)
+    }
+}
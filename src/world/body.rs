@@ -1,13 +1,18 @@
-use super::temporal::Temporal;
-use crate::Result;
+use super::environment::PlanetEnvironment;
 use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
 pub enum BodyType {
     Planet,
     Asteroid,
     Station,
+    Moon,
+    GasGiant,
+    Comet,
+    AsteroidBelt,
+    Ring,
 }
 
 impl From<u32> for BodyType {
@@ -16,31 +21,70 @@ impl From<u32> for BodyType {
             0 => BodyType::Planet,
             1 => BodyType::Asteroid,
             2 => BodyType::Station,
+            3 => BodyType::Moon,
+            4 => BodyType::GasGiant,
+            5 => BodyType::Comet,
+            6 => BodyType::AsteroidBelt,
+            7 => BodyType::Ring,
             _ => panic!("Invalid body type!"),
         }
     }
 }
 
+impl BodyType {
+    pub fn default_radius(&self) -> Option<f32> {
+        match self {
+            BodyType::Planet => Some(2.0e6),
+            BodyType::Moon => Some(2.0e5),
+            BodyType::GasGiant => Some(2.0e7),
+            BodyType::Asteroid => Some(500.),
+            BodyType::Station => Some(100.),
+            BodyType::Comet => Some(2_000.),
+            BodyType::AsteroidBelt | BodyType::Ring => None,
+        } // meter
+    }
+
+    pub fn can_orbit(&self, parent: BodyType) -> bool {
+        match self {
+            BodyType::Moon | BodyType::Ring => {
+                matches!(parent, BodyType::Planet | BodyType::GasGiant)
+            }
+            BodyType::Asteroid => parent == BodyType::AsteroidBelt,
+            BodyType::Station => true,
+            BodyType::Planet | BodyType::GasGiant | BodyType::Comet | BodyType::AsteroidBelt => {
+                false
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Body {
+    #[serde(default)]
+    pub uuid: Uuid,
     pub body_type: BodyType,
-    pub coords: Vector3<f32>,
-    pub velocity: Vector3<f32>,
+    #[serde(default)]
+    pub parent_uuid: Option<Uuid>,
+    pub coords: Vector3<f32>,   // relative to the parent when there is one
+    pub velocity: Vector3<f32>, // relative to the parent when there is one
+    #[serde(default)]
+    pub environment: Option<PlanetEnvironment>,
 }
 
 impl Body {
     pub fn new(body_type: BodyType, coords: Vector3<f32>) -> Body {
         Self {
+            uuid: Uuid::new_v4(),
             body_type,
+            parent_uuid: None,
             coords,
             velocity: Vector3::default(),
+            environment: None,
         }
     }
-}
 
-impl Temporal for Body {
-    fn update(&mut self, delta: f32) -> Result<()> {
-        self.coords += self.velocity * delta;
-        Ok(())
+    pub fn with_parent(mut self, parent_uuid: Uuid) -> Body {
+        self.parent_uuid = Some(parent_uuid);
+        self
     }
 }
@@ -0,0 +1,52 @@
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_TIME_SCALE: f64 = 10_000.;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SimulationClock {
+    pub time: f64, // second
+    pub time_scale: f64,
+    pub paused: bool,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            time: 0.,
+            time_scale: 1.,
+            paused: false,
+        }
+    }
+}
+
+impl SimulationClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn simulated_delta(&self, real_delta: f32) -> f32 {
+        if self.paused {
+            0.
+        } else {
+            (real_delta as f64 * self.time_scale) as f32
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f64) -> Result<()> {
+        if !time_scale.is_finite() || time_scale <= 0. || time_scale > MAX_TIME_SCALE {
+            return Err(Error::InvalidTimeScale);
+        }
+        self.time_scale = time_scale;
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    structure::{Structure, BLOCK_HALF_DIAGONAL, BLOCK_SIZE},
+    system::System,
+};
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+pub const COLLISION_CELL_SIZE: f32 = 1_000.; // meter
+pub const PLAYER_RADIUS: f32 = 1.; // meter
+pub const RESTITUTION: f32 = 0.2;
+
+type CellCoords = Vector3<i64>;
+
+#[derive(Debug, Clone, Default)]
+pub struct SpatialHash {
+    cells: HashMap<CellCoords, Vec<Uuid>>,
+    large: Vec<Uuid>,
+}
+
+impl SpatialHash {
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.large.clear();
+    }
+
+    pub fn insert(&mut self, uuid: Uuid, center: &Vector3<f32>, radius: f32) {
+        if radius > COLLISION_CELL_SIZE / 2. {
+            self.large.push(uuid);
+        } else {
+            self.cells
+                .entry(cell_coords(center))
+                .or_default()
+                .push(uuid);
+        }
+    }
+
+    pub fn candidate_pairs(&self) -> BTreeSet<(Uuid, Uuid)> {
+        let mut pairs = BTreeSet::new();
+        let mut add = |first: Uuid, second: Uuid| {
+            if first != second {
+                pairs.insert((first.min(second), first.max(second)));
+            }
+        };
+        for (cell, uuids) in self.cells.iter() {
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let Some(neighbors) = self.cells.get(&(cell + Vector3::new(x, y, z)))
+                        else {
+                            continue;
+                        };
+                        for first in uuids {
+                            for second in neighbors {
+                                add(*first, *second);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for large in self.large.iter() {
+            for uuids in self.cells.values() {
+                for uuid in uuids {
+                    add(*large, *uuid);
+                }
+            }
+            for other in self.large.iter() {
+                add(*large, *other);
+            }
+        }
+        pairs
+    }
+}
+
+fn cell_coords(center: &Vector3<f32>) -> CellCoords {
+    center.map(|coord| (coord / COLLISION_CELL_SIZE).floor() as i64)
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Contact {
+    pub first_uuid: Uuid,
+    pub second_uuid: Uuid,
+    pub normal: Vector3<f32>,
+    pub depth: f32, // meter
+}
+
+enum Collider<'a> {
+    Sphere {
+        center: Vector3<f32>,
+        radius: f32,
+    },
+    Structure {
+        origin: Vector3<f32>,
+        orientation: UnitQuaternion<f32>,
+        structure: &'a Structure,
+    },
+}
+
+impl Collider<'_> {
+    fn center(&self) -> Vector3<f32> {
+        match self {
+            Collider::Sphere { center, .. } => *center,
+            Collider::Structure { origin, .. } => *origin,
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        match self {
+            Collider::Sphere { radius, .. } => *radius,
+            Collider::Structure { structure, .. } => structure.bounding_radius(),
+        }
+    }
+}
+
+fn collider<'a>(system: &'a System, uuid: &Uuid) -> Option<Collider<'a>> {
+    let entities = &system.entities;
+    let center = system.absolute_coords(uuid)?;
+    if let Some(structure) = entities.structures.get(uuid) {
+        if structure.is_empty() {
+            return None;
+        }
+        return Some(Collider::Structure {
+            origin: center,
+            orientation: entities.orientations.get(uuid).copied().unwrap_or_default(),
+            structure,
+        });
+    }
+    let radius = if entities.players.contains(uuid) {
+        PLAYER_RADIUS
+    } else {
+        system.body_radius(uuid)?
+    };
+    Some(Collider::Sphere { center, radius })
+}
+
+fn sphere_structure(
+    center: &Vector3<f32>,
+    radius: f32,
+    origin: &Vector3<f32>,
+    orientation: &UnitQuaternion<f32>,
+    structure: &Structure,
+) -> Option<(Vector3<f32>, f32)> {
+    let local = orientation.inverse() * (center - origin);
+    let half = BLOCK_SIZE / 2.;
+    let mut deepest: Option<(Vector3<f32>, f32)> = None;
+    for (position, _block_type) in structure.blocks() {
+        let block_center = position.map(|coord| coord as f32) * BLOCK_SIZE;
+        if (local - block_center).norm() > radius + BLOCK_HALF_DIAGONAL {
+            continue;
+        }
+        let offset = local - block_center;
+        let closest = offset.map(|coord| coord.clamp(-half, half));
+        let outside = offset - closest;
+        let (normal, depth) = if outside.norm() > 0. {
+            (outside.normalize(), radius - outside.norm())
+        } else {
+            // The center is inside the block: push out through the nearest
+            // face.
+            let axis = offset.iamax();
+            let mut normal = Vector3::zeros();
+            normal[axis] = if offset[axis] < 0. { -1. } else { 1. };
+            (normal, radius + half - offset[axis].abs())
+        };
+        if depth > 0. && deepest.is_none_or(|(_normal, deepest)| depth > deepest) {
+            deepest = Some((orientation * normal, depth));
+        }
+    }
+    deepest
+}
+
+fn contact(first: &Collider, second: &Collider) -> Option<(Vector3<f32>, f32)> {
+    let distance = (second.center() - first.center()).norm();
+    if distance >= first.radius() + second.radius() {
+        return None;
+    }
+    match (first, second) {
+        (Collider::Sphere { center, radius }, Collider::Sphere { .. }) => {
+            let offset = second.center() - center;
+            let normal = if distance > 0. {
+                offset / distance
+            } else {
+                Vector3::x()
+            };
+            Some((normal, radius + second.radius() - distance))
+        }
+        (
+            Collider::Structure {
+                origin,
+                orientation,
+                structure,
+            },
+            Collider::Sphere { center, radius },
+        ) => sphere_structure(center, *radius, origin, orientation, structure),
+        (Collider::Sphere { .. }, Collider::Structure { .. }) => {
+            contact(second, first).map(|(normal, depth)| (-normal, depth))
+        }
+        (
+            Collider::Structure {
+                origin,
+                orientation,
+                structure,
+            },
+            Collider::Structure {
+                origin: other_origin,
+                orientation: other_orientation,
+                structure: other,
+            },
+        ) => {
+            if structure.len() > other.len() {
+                return contact(second, first).map(|(normal, depth)| (-normal, depth));
+            }
+            let mut deepest: Option<(Vector3<f32>, f32)> = None;
+            for (position, _block_type) in structure.blocks() {
+                let block_center =
+                    origin + orientation * position.map(|coord| coord as f32) * BLOCK_SIZE;
+                if (block_center - other_origin).norm()
+                    > other.bounding_radius() + BLOCK_HALF_DIAGONAL
+                {
+                    continue;
+                }
+                let Some((normal, depth)) = sphere_structure(
+                    &block_center,
+                    BLOCK_SIZE / 2.,
+                    other_origin,
+                    other_orientation,
+                    other,
+                ) else {
+                    continue;
+                };
+                if deepest.is_none_or(|(_normal, deepest)| depth > deepest) {
+                    deepest = Some((-normal, depth));
+                }
+            }
+            deepest
+        }
+    }
+}
+
+pub fn overlaps(system: &System, origin: &Vector3<f32>, structure: &Structure) -> bool {
+    if structure.is_empty() {
+        return false;
+    }
+    let placed = Collider::Structure {
+        origin: *origin,
+        orientation: UnitQuaternion::identity(),
+        structure,
+    };
+    system
+        .entities
+        .iter()
+        .filter_map(|uuid| collider(system, uuid))
+        .any(|other| contact(&placed, &other).is_some())
+}
+
+pub fn detect_collisions(system: &System, hash: &mut SpatialHash) -> Vec<Contact> {
+    hash.clear();
+    let mut colliders = HashMap::new();
+    for uuid in system.entities.iter() {
+        if let Some(collider) = collider(system, uuid) {
+            hash.insert(*uuid, &collider.center(), collider.radius());
+            colliders.insert(*uuid, collider);
+        }
+    }
+
+    let mut contacts = Vec::new();
+    for (first_uuid, second_uuid) in hash.candidate_pairs() {
+        if system.entities.body_types.contains_key(&first_uuid)
+            && system.entities.body_types.contains_key(&second_uuid)
+        {
+            continue;
+        }
+        // Docked entities don't collide with what they are attached to.
+        if system.entities.parents.get(&first_uuid) == Some(&second_uuid)
+            || system.entities.parents.get(&second_uuid) == Some(&first_uuid)
+        {
+            continue;
+        }
+        if let Some((normal, depth)) = contact(&colliders[&first_uuid], &colliders[&second_uuid]) {
+            contacts.push(Contact {
+                first_uuid,
+                second_uuid,
+                normal,
+                depth,
+            });
+        }
+    }
+    contacts
+}
+
+fn inverse_mass(system: &System, uuid: &Uuid) -> f32 {
+    // Attached entities move with what they are attached to.
+    if system.entities.body_types.contains_key(uuid) || system.entities.parents.contains_key(uuid) {
+        return 0.;
+    }
+    match system.entities.masses.get(uuid) {
+        Some(mass) if *mass > 0. => 1. / mass,
+        _ => 0.,
+    }
+}
+
+pub fn resolve_collisions(system: &mut System, contacts: Vec<Contact>) -> Vec<Contact> {
+    let mut impacts = Vec::new();
+    for contact in contacts {
+        let first_inverse_mass = inverse_mass(system, &contact.first_uuid);
+        let second_inverse_mass = inverse_mass(system, &contact.second_uuid);
+        let total_inverse_mass = first_inverse_mass + second_inverse_mass;
+        if total_inverse_mass <= 0. {
+            continue;
+        }
+
+        let correction = contact.normal * contact.depth / total_inverse_mass;
+        if let Some(position) = system.entities.positions.get_mut(&contact.first_uuid) {
+            *position -= correction * first_inverse_mass;
+        }
+        if let Some(position) = system.entities.positions.get_mut(&contact.second_uuid) {
+            *position += correction * second_inverse_mass;
+        }
+
+        let relative_velocity = system
+            .absolute_velocity(&contact.second_uuid)
+            .unwrap_or_default()
+            - system
+                .absolute_velocity(&contact.first_uuid)
+                .unwrap_or_default();
+        let closing_speed = relative_velocity.dot(&contact.normal);
+        if closing_speed >= 0. {
+            continue;
+        }
+        let impulse = contact.normal * -(1. + RESTITUTION) * closing_speed / total_inverse_mass;
+        if let Some(velocity) = system.entities.velocities.get_mut(&contact.first_uuid) {
+            *velocity -= impulse * first_inverse_mass;
+        }
+        if let Some(velocity) = system.entities.velocities.get_mut(&contact.second_uuid) {
+            *velocity += impulse * second_inverse_mass;
+        }
+        impacts.push(contact);
+    }
+    impacts
+}
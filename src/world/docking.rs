@@ -0,0 +1,27 @@
+use super::{body::BodyType, system::System};
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const MAX_DOCKING_DISTANCE: f32 = 50.; // meter, from the station's surface
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum StationService {
+    Repair,
+}
+
+pub fn check_docking(system: &System, uuid: &Uuid, station_uuid: &Uuid) -> Result<()> {
+    if system.entities.body_types.get(station_uuid) != Some(&BodyType::Station) {
+        return Err(Error::NotAStation);
+    }
+    let station_coords = system
+        .absolute_coords(station_uuid)
+        .ok_or(Error::NoBodyForUuid)?;
+    let coords = system.absolute_coords(uuid).ok_or(Error::NoPlayerForUuid)?;
+    let radius = system.body_radius(station_uuid).unwrap_or_default();
+    if (coords - station_coords).norm() - radius > MAX_DOCKING_DISTANCE {
+        return Err(Error::OutOfDockingRange);
+    }
+    Ok(())
+}
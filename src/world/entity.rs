@@ -0,0 +1,196 @@
+use super::{
+    body::BodyType,
+    environment::PlanetEnvironment,
+    flight::FlightControls,
+    inventory::Inventory,
+    mining::Deposit,
+    orbit::orbiting_bodies,
+    production::ProductionJob,
+    structure::{BlockCoordsRepr, Structure},
+};
+use crate::SystemCoordsRepr;
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default)]
+pub struct SpawnOrder {
+    ranks: HashMap<Uuid, u64>,
+    order: BTreeMap<u64, Uuid>,
+    next_rank: u64,
+}
+
+impl SpawnOrder {
+    pub fn insert(&mut self, uuid: Uuid) -> bool {
+        if self.ranks.contains_key(&uuid) {
+            return false;
+        }
+        self.ranks.insert(uuid, self.next_rank);
+        self.order.insert(self.next_rank, uuid);
+        self.next_rank += 1;
+        true
+    }
+
+    pub fn remove(&mut self, uuid: &Uuid) -> bool {
+        let Some(rank) = self.ranks.remove(uuid) else {
+            return false;
+        };
+        self.order.remove(&rank);
+        true
+    }
+
+    pub fn contains(&self, uuid: &Uuid) -> bool {
+        self.ranks.contains_key(uuid)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Uuid> {
+        self.order.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+}
+
+impl Serialize for SpawnOrder {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for SpawnOrder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut spawned = SpawnOrder::default();
+        for uuid in Vec::<Uuid>::deserialize(deserializer)? {
+            spawned.insert(uuid);
+        }
+        Ok(spawned)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Entities {
+    #[serde(default)]
+    spawned: SpawnOrder,
+    #[serde(default)]
+    pub positions: BTreeMap<Uuid, Vector3<SystemCoordsRepr>>, // relative to the parent when there is one
+    #[serde(default)]
+    pub velocities: BTreeMap<Uuid, Vector3<f32>>, // relative to the parent when there is one
+    #[serde(default)]
+    pub orientations: BTreeMap<Uuid, UnitQuaternion<f32>>,
+    #[serde(default)]
+    pub angular_velocities: BTreeMap<Uuid, Vector3<f32>>, // radian/second, in the entity's frame
+    #[serde(default)]
+    pub flight_controls: BTreeMap<Uuid, FlightControls>,
+    #[serde(default)]
+    pub masses: BTreeMap<Uuid, f32>, // kg
+    #[serde(default)]
+    pub owners: BTreeMap<Uuid, Uuid>, // player uuid
+    #[serde(default)]
+    pub healths: BTreeMap<Uuid, f32>,
+    #[serde(default)]
+    pub parents: BTreeMap<Uuid, Uuid>,
+    #[serde(default)]
+    pub body_types: BTreeMap<Uuid, BodyType>,
+    #[serde(default)]
+    pub environments: BTreeMap<Uuid, PlanetEnvironment>,
+    #[serde(default)]
+    pub deposits: BTreeMap<Uuid, Vec<Deposit>>,
+    #[serde(default)]
+    pub structures: BTreeMap<Uuid, Structure>,
+    #[serde(default)]
+    pub inventories: BTreeMap<Uuid, Inventory>,
+    #[serde(default)]
+    pub productions: BTreeMap<Uuid, Vec<ProductionJob>>,
+    #[serde(default)]
+    pub structure_chunks: BTreeMap<Uuid, Vec<Vector3<BlockCoordsRepr>>>,
+    #[serde(default)]
+    pub players: BTreeSet<Uuid>,
+}
+
+impl Entities {
+    pub fn spawn(&mut self, uuid: Uuid) -> Uuid {
+        self.spawned.insert(uuid);
+        uuid
+    }
+
+    pub fn despawn(&mut self, uuid: &Uuid) -> bool {
+        if !self.spawned.remove(uuid) {
+            return false;
+        }
+        self.positions.remove(uuid);
+        self.velocities.remove(uuid);
+        self.orientations.remove(uuid);
+        self.angular_velocities.remove(uuid);
+        self.flight_controls.remove(uuid);
+        self.masses.remove(uuid);
+        self.owners.remove(uuid);
+        self.healths.remove(uuid);
+        self.parents.remove(uuid);
+        self.body_types.remove(uuid);
+        self.environments.remove(uuid);
+        self.deposits.remove(uuid);
+        self.structures.remove(uuid);
+        self.inventories.remove(uuid);
+        self.productions.remove(uuid);
+        self.players.remove(uuid);
+        true
+    }
+
+    // Copy of the entities without structure blocks, which clients get apart.
+    pub fn without_blocks(&self) -> Entities {
+        Entities {
+            spawned: self.spawned.clone(),
+            positions: self.positions.clone(),
+            velocities: self.velocities.clone(),
+            orientations: self.orientations.clone(),
+            angular_velocities: self.angular_velocities.clone(),
+            flight_controls: self.flight_controls.clone(),
+            masses: self.masses.clone(),
+            owners: self.owners.clone(),
+            healths: self.healths.clone(),
+            parents: self.parents.clone(),
+            body_types: self.body_types.clone(),
+            environments: self.environments.clone(),
+            deposits: self.deposits.clone(),
+            structures: BTreeMap::new(),
+            inventories: self.inventories.clone(),
+            productions: self.productions.clone(),
+            structure_chunks: BTreeMap::new(),
+            players: self.players.clone(),
+        }
+    }
+
+    pub fn contains(&self, uuid: &Uuid) -> bool {
+        self.spawned.contains(uuid)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Uuid> {
+        self.spawned.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.spawned.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spawned.is_empty()
+    }
+
+    pub fn integrate_velocities(&mut self, delta: f32) {
+        let orbiting = orbiting_bodies(self);
+        for (uuid, position) in self.positions.iter_mut() {
+            if orbiting.contains(uuid) {
+                continue;
+            }
+            if let Some(velocity) = self.velocities.get(uuid) {
+                *position += velocity * delta;
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use super::{body::BodyType, hazard::GRAVITATIONAL_CONSTANT};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+pub const METERS_PER_AU: f64 = 1.496e11;
+pub const COSMIC_BACKGROUND_TEMPERATURE: f64 = 2.7; // kelvin
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum AtmosphereType {
+    None,
+    Thin,
+    Breathable,
+    Toxic,
+    Dense,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum Biome {
+    Barren,
+    Desert,
+    Ocean,
+    Temperate,
+    Jungle,
+    Ice,
+    Volcanic,
+    GasGiant,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PlanetEnvironment {
+    pub radius: f64,          // meter
+    pub surface_gravity: f64, // meter/second²
+    pub atmosphere: AtmosphereType,
+    pub temperature: f64,       // kelvin
+    pub resource_richness: f64, // 0 (depleted) to 1 (very rich)
+    pub biome: Biome,
+}
+
+impl PlanetEnvironment {
+    pub fn mass(&self) -> f64 {
+        self.surface_gravity * self.radius.powi(2) / GRAVITATIONAL_CONSTANT
+    }
+
+    pub fn equilibrium_temperature(luminosity: f64, distance: f64) -> f64 {
+        let distance = (distance / METERS_PER_AU).max(f64::EPSILON);
+        (278.6 * luminosity.powf(0.25) / distance.sqrt()).max(COSMIC_BACKGROUND_TEMPERATURE)
+    }
+
+    pub fn generate(
+        body_type: BodyType,
+        luminosity: f64,
+        distance: f64,
+        seed: u64,
+    ) -> Option<PlanetEnvironment> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (radius, density) = match body_type {
+            BodyType::Planet => (rng.gen_range(2.0e6..1.2e7), rng.gen_range(3000. ..6000.)),
+            BodyType::Moon => (rng.gen_range(2.0e5..3.0e6), rng.gen_range(2500. ..4000.)),
+            BodyType::GasGiant => (rng.gen_range(2.0e7..8.0e7), rng.gen_range(700. ..1600.)),
+            _ => return None,
+        };
+        let surface_gravity = 4. / 3. * PI * GRAVITATIONAL_CONSTANT * density * radius;
+        let temperature = Self::equilibrium_temperature(luminosity, distance);
+        let resource_richness = rng.gen_range(0. ..1.);
+
+        if body_type == BodyType::GasGiant {
+            return Some(Self {
+                radius,
+                surface_gravity,
+                atmosphere: AtmosphereType::Dense,
+                temperature,
+                resource_richness,
+                biome: Biome::GasGiant,
+            });
+        }
+
+        let habitable_temperature = (250. ..=320.).contains(&temperature);
+        let atmosphere = match surface_gravity {
+            gravity if gravity < 2. => AtmosphereType::None,
+            gravity if gravity < 6. => AtmosphereType::Thin,
+            _ => match rng.gen_range(0..3) {
+                0 if habitable_temperature => AtmosphereType::Breathable,
+                0 | 1 => AtmosphereType::Toxic,
+                _ => AtmosphereType::Dense,
+            },
+        };
+
+        let biome = match (atmosphere, temperature) {
+            (_, temperature) if temperature > 500. => Biome::Volcanic,
+            (_, temperature) if temperature < 240. => Biome::Ice,
+            (AtmosphereType::None | AtmosphereType::Thin, _) => Biome::Barren,
+            (AtmosphereType::Breathable, _) => match rng.gen_range(0..3) {
+                0 => Biome::Ocean,
+                1 => Biome::Temperate,
+                _ => Biome::Jungle,
+            },
+            _ => Biome::Desert,
+        };
+
+        Some(Self {
+            radius,
+            surface_gravity,
+            atmosphere,
+            temperature,
+            resource_richness,
+            biome,
+        })
+    }
+}
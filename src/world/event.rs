@@ -0,0 +1,194 @@
+use super::{galaxy::Galaxy, production::RecipeId};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub enum WorldEvent {
+    PlayerJoined {
+        player_uuid: Uuid,
+        nickname: String,
+    },
+    PlayerLeft {
+        player_uuid: Uuid,
+    },
+    SystemCreated {
+        system_uuid: Uuid,
+    },
+    EnteredSystem {
+        player_uuid: Uuid,
+        system_uuid: Uuid,
+    },
+    LeftSystem {
+        player_uuid: Uuid,
+        system_uuid: Uuid,
+    },
+    HyperjumpStarted {
+        player_uuid: Uuid,
+        origin_system_uuid: Uuid,
+        target_system_uuid: Uuid,
+    },
+    PlayerKilled {
+        player_uuid: Uuid,
+        system_uuid: Uuid,
+    },
+    EntityDestroyed {
+        entity_uuid: Uuid,
+        system_uuid: Uuid,
+    },
+    Collision {
+        system_uuid: Uuid,
+        first_uuid: Uuid,
+        second_uuid: Uuid,
+    },
+    Docked {
+        player_uuid: Uuid,
+        station_uuid: Uuid,
+        system_uuid: Uuid,
+    },
+    Undocked {
+        player_uuid: Uuid,
+        station_uuid: Uuid,
+        system_uuid: Uuid,
+    },
+    ProductionFinished {
+        owner_uuid: Uuid,
+        facility_uuid: Uuid,
+        recipe: RecipeId,
+        system_uuid: Uuid,
+    },
+}
+
+impl WorldEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WorldEvent::PlayerJoined { .. } => "PlayerJoined",
+            WorldEvent::PlayerLeft { .. } => "PlayerLeft",
+            WorldEvent::SystemCreated { .. } => "SystemCreated",
+            WorldEvent::EnteredSystem { .. } => "EnteredSystem",
+            WorldEvent::LeftSystem { .. } => "LeftSystem",
+            WorldEvent::HyperjumpStarted { .. } => "HyperjumpStarted",
+            WorldEvent::PlayerKilled { .. } => "PlayerKilled",
+            WorldEvent::EntityDestroyed { .. } => "EntityDestroyed",
+            WorldEvent::Collision { .. } => "Collision",
+            WorldEvent::Docked { .. } => "Docked",
+            WorldEvent::Undocked { .. } => "Undocked",
+            WorldEvent::ProductionFinished { .. } => "ProductionFinished",
+        }
+    }
+
+    pub fn system_uuid(&self) -> Option<Uuid> {
+        match self {
+            WorldEvent::PlayerJoined { .. } | WorldEvent::PlayerLeft { .. } => None,
+            WorldEvent::SystemCreated { system_uuid }
+            | WorldEvent::EnteredSystem { system_uuid, .. }
+            | WorldEvent::LeftSystem { system_uuid, .. }
+            | WorldEvent::PlayerKilled { system_uuid, .. }
+            | WorldEvent::EntityDestroyed { system_uuid, .. }
+            | WorldEvent::Collision { system_uuid, .. }
+            | WorldEvent::Docked { system_uuid, .. }
+            | WorldEvent::Undocked { system_uuid, .. }
+            | WorldEvent::ProductionFinished { system_uuid, .. } => Some(*system_uuid),
+            WorldEvent::HyperjumpStarted {
+                origin_system_uuid, ..
+            } => Some(*origin_system_uuid),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct TimedEvent {
+    pub tick: u64,
+    pub time: f64, // second, galaxy time
+    pub event: WorldEvent,
+}
+
+pub trait EventSubscriber: Send {
+    fn on_event(&mut self, galaxy: &mut Galaxy, event: &TimedEvent) -> Result<()>;
+}
+
+pub struct EventLogger;
+
+impl EventSubscriber for EventLogger {
+    fn on_event(&mut self, _galaxy: &mut Galaxy, event: &TimedEvent) -> Result<()> {
+        tracing::info!("[tick {}] {:?}", event.tick, event.event);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct EventStats {
+    counts: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+impl EventStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn counts(&self) -> BTreeMap<String, u64> {
+        self.counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl EventSubscriber for EventStats {
+    fn on_event(&mut self, _galaxy: &mut Galaxy, event: &TimedEvent) -> Result<()> {
+        *self
+            .counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(event.event.kind().to_string())
+            .or_default() += 1;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    tick: u64,
+    pending: Vec<TimedEvent>,
+    last_tick: Vec<TimedEvent>,
+    pub(crate) subscribers: Vec<Box<dyn EventSubscriber>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&mut self, time: f64, event: WorldEvent) {
+        self.pending.push(TimedEvent {
+            tick: self.tick,
+            time,
+            event,
+        });
+    }
+
+    pub fn subscribe(&mut self, subscriber: Box<dyn EventSubscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn last_tick(&self) -> &[TimedEvent] {
+        &self.last_tick
+    }
+
+    pub(crate) fn end_tick(&mut self) -> Vec<TimedEvent> {
+        self.tick += 1;
+        std::mem::take(&mut self.pending)
+    }
+
+    pub(crate) fn set_last_tick(&mut self, events: Vec<TimedEvent>) {
+        self.last_tick = events;
+    }
+}
@@ -0,0 +1,60 @@
+use super::entity::Entities;
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct FlightControls {
+    pub thrust: Vector3<f32>,
+    pub torque: Vector3<f32>,
+}
+
+impl FlightControls {
+    pub fn clamped(&self) -> Self {
+        Self {
+            thrust: self.thrust.map(|axis| axis.clamp(-1., 1.)),
+            torque: self.torque.map(|axis| axis.clamp(-1., 1.)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct FlightInput {
+    pub structure_uuid: Uuid,
+    pub controls: FlightControls,
+}
+
+pub fn apply_flight(entities: &mut Entities, delta: f32) {
+    for (uuid, structure) in entities.structures.iter() {
+        let controls = entities
+            .flight_controls
+            .get(uuid)
+            .copied()
+            .unwrap_or_default()
+            .clamped();
+        let orientation = entities
+            .orientations
+            .entry(*uuid)
+            .or_insert_with(UnitQuaternion::identity);
+        let angular_velocity = entities.angular_velocities.entry(*uuid).or_default();
+
+        let mass = structure.mass();
+        if mass > 0. {
+            let thrust = structure.effective_thrust();
+            let force = *orientation * (controls.thrust * thrust);
+            *entities.velocities.entry(*uuid).or_default() += force / mass * delta;
+
+            // Thrusters spin the structure with a lever arm as long as its
+            // radius of gyration.
+            let inertia = structure.inertia_tensor();
+            let radius = (inertia.trace() / (2. * mass)).sqrt();
+            let torque = controls.torque * thrust * radius;
+            if let Some(inverse_inertia) = inertia.try_inverse() {
+                let gyroscopic = angular_velocity.cross(&(inertia * *angular_velocity));
+                *angular_velocity += inverse_inertia * (torque - gyroscopic) * delta;
+            }
+        }
+
+        *orientation *= UnitQuaternion::from_scaled_axis(*angular_velocity * delta);
+    }
+}
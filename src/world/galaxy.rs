@@ -1,69 +1,416 @@
-use super::{player::Player, system::System, temporal::Temporal};
+use super::{
+    blueprint::{self, Blueprint, BlueprintRef, BlueprintSummary, MAX_BLUEPRINT_BLOCKS},
+    body::BodyType,
+    clock::SimulationClock,
+    collision,
+    docking::{self, StationService},
+    event::{EventBus, WorldEvent},
+    flight::FlightInput,
+    hazard::{self, HazardReport},
+    hyperjump::{Hyperjump, MAX_HYPERJUMP_RANGE},
+    inventory::{Inventory, InventoryRef, ItemStack, ItemTransfer, MAX_TRANSFER_DISTANCE},
+    item::ItemCatalog,
+    mining::{MiningRequest, MiningResult, MAX_MINING_DISTANCE, MINING_COOLDOWN, MINING_YIELD},
+    player::{Player, MAX_PLAYER_HEALTH, PLAYER_SPAWN_COORDS},
+    production::{
+        production_slots, ProductionJob, ProductionOrder, RecipeCatalog, MAX_PRODUCTION_QUEUE,
+    },
+    route::{self, JumpRoute},
+    structure::{
+        BlockCoordsRepr, BlockEdit, Structure, StructureChange, BLOCK_SIZE, MAX_BUILD_RANGE,
+    },
+    system::{System, SystemSummary},
+    system_cache::SystemCache,
+    system_index::SystemIndex,
+    temporal::Temporal,
+};
+use crate::config::ServerConfig;
 use crate::error::Error;
 use crate::Result;
+use nalgebra::Vector3;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
 use redis::{Commands, RedisResult};
 use regex::Regex;
-use std::{collections::HashMap, str::FromStr};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+    time::Instant,
+};
 use uuid::Uuid;
 
+pub fn update_systems(
+    thread_pool: &ThreadPool,
+    systems: &mut HashMap<Uuid, System>,
+    uuids: &HashSet<Uuid>,
+    time: f64,
+) -> Result<()> {
+    // Only the observed systems are taken out of the map, so the tick doesn't
+    // walk every loaded one.
+    let mut observed: Vec<(Uuid, System)> = uuids
+        .iter()
+        .filter_map(|uuid| systems.remove_entry(uuid))
+        .collect();
+    let result = thread_pool.install(|| {
+        observed
+            .par_iter_mut()
+            .try_for_each(|(_uuid, system)| system.catch_up(time))
+    });
+    systems.extend(observed);
+    result
+}
+
+fn connected(connection: &mut Option<redis::Connection>) -> Result<&mut redis::Connection> {
+    connection.as_mut().ok_or(Error::OfflineGalaxy)
+}
+
+fn inventory_mut<'a>(
+    players: &'a mut HashMap<Uuid, Player>,
+    systems: &'a mut HashMap<Uuid, System>,
+    player_uuid: &Uuid,
+    inventory_ref: InventoryRef,
+) -> Result<&'a mut Inventory> {
+    let player = players.get_mut(player_uuid).ok_or(Error::NoPlayerForUuid)?;
+    match inventory_ref {
+        InventoryRef::Player => Ok(&mut player.inventory),
+        InventoryRef::Structure(structure_uuid) => systems
+            .get_mut(&player.current_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?
+            .cargo_mut(&structure_uuid),
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct GalaxyExport {
+    pub clock: SimulationClock,
+    pub systems: HashMap<Uuid, System>,
+    pub players: HashMap<Uuid, Player>,
+}
+
+impl GalaxyExport {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::ExportReadError)?;
+        serde_json::from_str(&json).map_err(Error::ExportDeserializationError)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(Error::ExportSerializationError)?;
+        std::fs::write(path, json).map_err(Error::ExportWriteError)
+    }
+}
+
 pub struct Galaxy {
-    pub connection: redis::Connection,
+    connection: Option<redis::Connection>, // none for offline galaxies
+    pub read_only: bool,                   // migrated systems are not saved back
     pub systems: HashMap<Uuid, System>,
     pub players: HashMap<Uuid, Player>,
+    pub system_index: SystemIndex,
+    pub system_cache: SystemCache,
+    pub clock: SimulationClock,
+    pub thread_pool: ThreadPool,
     pub db_name: String,
     pub rotation_speed: f32,
+    pub events: EventBus,
+    pub catalog: ItemCatalog,
+    pub recipes: RecipeCatalog,
+    mining_cooldowns: HashMap<Uuid, f64>, // galaxy time a player may mine again
 }
 
 impl Galaxy {
     pub fn new(db_name: &str) -> Result<Self> {
+        Self::with_config(db_name, &ServerConfig::default())
+    }
+
+    pub fn with_config(db_name: &str, config: &ServerConfig) -> Result<Self> {
         let client =
             redis::Client::open("redis://127.0.0.1/").map_err(|_| Error::RedisOpenError)?;
-        let connection = client
-            .get_connection()
-            .map_err(|err| Error::RedisGetConnError(err))?;
+        let connection = client.get_connection().map_err(Error::RedisGetConnError)?;
+        Self::build(Some(connection), db_name, config)
+    }
+
+    pub fn offline(config: &ServerConfig) -> Result<Self> {
+        Self::build(None, "offline", config)
+    }
+
+    fn build(
+        connection: Option<redis::Connection>,
+        db_name: &str,
+        config: &ServerConfig,
+    ) -> Result<Self> {
+        let thread_pool = ThreadPoolBuilder::new()
+            .thread_name(|index| format!("system-simulation-{index}"))
+            .build()
+            .map_err(Error::ThreadPoolBuildError)?;
+        let catalog = match &config.item_catalog {
+            Some(path) => ItemCatalog::load(path)?,
+            None => ItemCatalog::builtin().clone(),
+        };
+        let recipes = match &config.recipe_catalog {
+            Some(path) => RecipeCatalog::load(path)?,
+            None => RecipeCatalog::builtin().clone(),
+        };
+        recipes.check_items(&catalog)?;
 
         Ok(Self {
             connection,
+            read_only: false,
             systems: HashMap::new(),
             players: HashMap::new(),
+            system_index: SystemIndex::new(),
+            system_cache: SystemCache::new(config.system_cache.clone()),
+            clock: SimulationClock::new(),
+            thread_pool,
             db_name: db_name.to_string(),
             rotation_speed: 1.,
+            events: EventBus::new(),
+            catalog,
+            recipes,
+            mining_cooldowns: HashMap::new(),
         })
     }
 
-    pub fn add_system(&mut self, system: System) -> Uuid {
+    pub fn connection(&mut self) -> Result<&mut redis::Connection> {
+        connected(&mut self.connection)
+    }
+
+    pub fn disconnect(&mut self) {
+        self.connection = None;
+    }
+
+    pub fn add_system(&mut self, mut system: System) -> Uuid {
         let uuid = Uuid::new_v4();
+        system.simulated_time = system.simulated_time.max(self.clock.time);
+        self.system_index.insert(system.summary(uuid));
         self.systems.insert(uuid, system);
+        self.system_cache.touch(uuid, Instant::now());
+        self.system_cache.mark_dirty(uuid);
+        self.publish(WorldEvent::SystemCreated { system_uuid: uuid });
         uuid
     }
 
+    pub fn publish(&mut self, event: WorldEvent) {
+        self.events.publish(self.clock.time, event);
+    }
+
+    pub fn dispatch_events(&mut self) {
+        let events = self.events.end_tick();
+        let mut subscribers = std::mem::take(&mut self.events.subscribers);
+        for event in &events {
+            for subscriber in subscribers.iter_mut() {
+                if let Err(err) = subscriber.on_event(self, event) {
+                    tracing::warn!("Event subscriber failed on {:?}: {err}", event.event);
+                }
+            }
+        }
+        // Subscribers may subscribe others while handling events.
+        subscribers.append(&mut self.events.subscribers);
+        self.events.subscribers = subscribers;
+        self.events.set_last_tick(events);
+    }
+
+    fn structure_chunk_key(
+        &self,
+        structure_uuid: &Uuid,
+        chunk: &Vector3<BlockCoordsRepr>,
+    ) -> String {
+        format!(
+            "{}:structure_chunk:{}:{}:{}:{}",
+            self.db_name, structure_uuid, chunk.x, chunk.y, chunk.z
+        )
+    }
+
+    pub fn save_system(&mut self, uuid: &Uuid) -> Result<()> {
+        let Some(system) = self.systems.get_mut(uuid) else {
+            return Err(Error::NoSystemForUuid);
+        };
+
+        let mut chunk_writes: Vec<(Uuid, Vector3<BlockCoordsRepr>, Vec<u8>)> = Vec::new();
+        let mut manifest = BTreeMap::new();
+        for (structure_uuid, structure) in system.entities.structures.iter() {
+            for chunk in structure.dirty_chunks() {
+                chunk_writes.push((*structure_uuid, chunk, structure.encode_chunk(&chunk)));
+            }
+            manifest.insert(*structure_uuid, structure.chunk_coords());
+        }
+        // Chunks of destroyed structures.
+        for (structure_uuid, chunks) in system.entities.structure_chunks.iter() {
+            if !manifest.contains_key(structure_uuid) {
+                for chunk in chunks {
+                    chunk_writes.push((*structure_uuid, *chunk, Vec::new()));
+                }
+            }
+        }
+        // Saved with the new manifest, kept in memory once saved.
+        let saved_manifest = std::mem::replace(&mut system.entities.structure_chunks, manifest);
+        let structures = std::mem::take(&mut system.entities.structures);
+        let json = serde_json::to_string(system).map_err(Error::SaveSystemsSerializationError);
+        system.entities.structures = structures;
+        let manifest = std::mem::replace(&mut system.entities.structure_chunks, saved_manifest);
+        let json = json?;
+        let summary_json = serde_json::to_string(&system.summary(*uuid))
+            .map_err(Error::SaveSystemsSerializationError)?;
+
+        for (structure_uuid, chunk, bytes) in chunk_writes {
+            let key = self.structure_chunk_key(&structure_uuid, &chunk);
+            if bytes.is_empty() {
+                connected(&mut self.connection)?
+                    .del::<_, ()>(key)
+                    .map_err(Error::SaveChunkError)?;
+            } else {
+                connected(&mut self.connection)?
+                    .set::<_, _, ()>(key, bytes)
+                    .map_err(Error::SaveChunkError)?;
+            }
+        }
+        connected(&mut self.connection)?
+            .set::<_, _, ()>(format!("{}:system:{}", self.db_name, uuid), json)
+            .map_err(Error::SaveSystemsSetError)?;
+        connected(&mut self.connection)?
+            .set::<_, _, ()>(
+                format!("{}:system_summary:{}", self.db_name, uuid),
+                summary_json,
+            )
+            .map_err(Error::SaveSystemsSetError)?;
+
+        if let Some(system) = self.systems.get_mut(uuid) {
+            system.entities.structure_chunks = manifest;
+            for structure in system.entities.structures.values_mut() {
+                structure.take_dirty_chunks();
+            }
+        }
+        self.system_cache.mark_clean(uuid);
+        Ok(())
+    }
+
+    fn load_structures(&mut self, uuid: &Uuid) -> Result<()> {
+        let Some(system) = self.systems.get(uuid) else {
+            return Err(Error::NoSystemForUuid);
+        };
+        let to_load: Vec<(Uuid, Vec<Vector3<BlockCoordsRepr>>)> = system
+            .entities
+            .structure_chunks
+            .iter()
+            .filter(|(structure_uuid, _chunks)| {
+                system.entities.contains(structure_uuid)
+                    && !system.entities.structures.contains_key(structure_uuid)
+            })
+            .map(|(structure_uuid, chunks)| (*structure_uuid, chunks.clone()))
+            .collect();
+
+        for (structure_uuid, chunks) in to_load {
+            let mut structure = Structure::new();
+            if !chunks.is_empty() {
+                let keys: Vec<String> = chunks
+                    .iter()
+                    .map(|chunk| self.structure_chunk_key(&structure_uuid, chunk))
+                    .collect();
+                let encoded: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+                    .arg(keys)
+                    .query(connected(&mut self.connection)?)
+                    .map_err(Error::LoadChunkError)?;
+                for (chunk, bytes) in chunks.iter().zip(encoded) {
+                    structure.decode_chunk(chunk, &bytes.unwrap_or_default())?;
+                }
+            }
+            structure.take_dirty_chunks();
+            if let Some(system) = self.systems.get_mut(uuid) {
+                system.entities.structures.insert(structure_uuid, structure);
+            }
+        }
+        Ok(())
+    }
+
     pub fn save_systems(&mut self) -> Result<()> {
-        let mut jsons: Vec<(Uuid, String)> = Vec::new();
+        let uuids: Vec<Uuid> = self.systems.keys().copied().collect();
+        for uuid in uuids {
+            self.save_system(&uuid)?;
+        }
+        Ok(())
+    }
+
+    pub fn load_system(&mut self, uuid: &Uuid) -> Result<bool> {
+        if self.systems.contains_key(uuid) {
+            self.system_cache.touch(*uuid, Instant::now());
+            return Ok(false);
+        }
+        self.read_system(uuid, false)?;
+        Ok(true)
+    }
+
+    fn read_system(&mut self, uuid: &Uuid, save: bool) -> Result<()> {
+        let json: String = connected(&mut self.connection)?
+            .get(format!("{}:system:{}", self.db_name, uuid))
+            .map_err(|_| Error::NoSystemForUuid)?;
+        let system: System =
+            serde_json::from_str(&json).map_err(Error::SystemDeserializationError)?;
+
+        let migrated = self.insert_loaded_system(*uuid, system);
+        self.load_structures(uuid)?;
+        if (migrated || save) && !self.read_only {
+            self.save_system(uuid)?;
+        }
+        Ok(())
+    }
 
-        for (uuid, system) in &self.systems {
-            jsons.push((
-                *uuid,
-                serde_json::to_string(system)
-                    .map_err(|err| Error::SaveSystemsSerializationError(err))?,
-            ));
+    fn insert_loaded_system(&mut self, uuid: Uuid, mut system: System) -> bool {
+        // Galaxy time is at least the one of its most advanced system.
+        self.clock.time = self.clock.time.max(system.simulated_time);
+        let mut migrated = system.migrate_body_uuids();
+        migrated |= system.migrate_legacy_bodies();
+        migrated |= system.migrate_stellar_properties();
+        migrated |= system.migrate_environments();
+        migrated |= system.migrate_deposits();
+        migrated |= system.has_unchunked_structures();
+        self.system_index.insert(system.summary(uuid));
+        self.systems.insert(uuid, system);
+        self.system_cache.touch(uuid, Instant::now());
+        migrated
+    }
+
+    pub fn load_system_index(&mut self) -> Result<()> {
+        let summary_keys: Vec<String> = connected(&mut self.connection)?
+            .keys(format!("{}:system_summary:*", self.db_name))
+            .map_err(|_| Error::KeysQueryError)?;
+
+        for key in summary_keys {
+            let res: RedisResult<String> = connected(&mut self.connection)?.get(&key);
+            match res.map(|json| serde_json::from_str::<SystemSummary>(&json)) {
+                Ok(Ok(summary)) => self.system_index.insert(summary),
+                _ => tracing::warn!("Could not read system summary {key}"),
+            }
         }
 
-        for (uuid, json) in jsons {
-            self.connection
-                .set(format!("{}:system:{}", self.db_name, uuid), json)
-                .map_err(|err| Error::SaveSystemsSetError(err))?;
+        // Systems saved before summaries existed.
+        let system_keys: Vec<String> = connected(&mut self.connection)?
+            .keys(format!("{}:system:*", self.db_name))
+            .map_err(|_| Error::KeysQueryError)?;
+        let re = Regex::new(r"^.*system:(.*)$").unwrap();
+        for key in system_keys {
+            let Some(uuid) = re
+                .captures(&key)
+                .and_then(|captures| Uuid::from_str(&captures[1]).ok())
+            else {
+                continue;
+            };
+            // Loaded systems are always in the index, the others are saved
+            // again to get a summary.
+            if self.system_index.get(&uuid).is_none() {
+                self.read_system(&uuid, true)?;
+            }
         }
+
         Ok(())
     }
 
     pub fn load_systems(&mut self) -> Result<()> {
-        let keys: Vec<String> = self
-            .connection
+        let keys: Vec<String> = connected(&mut self.connection)?
             .keys(format!("{}:system:*", self.db_name))
             .map_err(|_| Error::KeysQueryError)?;
 
         let re = Regex::new(r"^.*system:(.*)$").unwrap();
 
+        let connection = connected(&mut self.connection)?;
         let systems: Vec<(Uuid, System)> = keys
             .iter()
             .filter_map(|system_key: &String| {
@@ -80,7 +427,7 @@ impl Galaxy {
                         return None;
                     }
                 };
-                let res: RedisResult<String> = self.connection.get(system_key);
+                let res: RedisResult<String> = connection.get(system_key);
                 match res {
                     Ok(val) => {
                         let res: serde_json::Result<System> = serde_json::from_str(&val);
@@ -94,8 +441,15 @@ impl Galaxy {
             })
             .collect();
 
+        let mut migrated = false;
         for (uuid, system) in systems {
-            self.systems.insert(uuid, system);
+            migrated |= self.insert_loaded_system(uuid, system);
+            self.load_structures(&uuid)?;
+        }
+
+        if migrated && !self.read_only {
+            tracing::info!("Systems from an older format were found, saving migrated systems");
+            self.save_systems()?;
         }
 
         Ok(())
@@ -113,7 +467,7 @@ impl Galaxy {
             .iter()
             .filter_map(
                 |(key, value): (&Uuid, &Player)| match serde_json::to_string(&value) {
-                    Ok(json) => Some((key.clone(), json, value.nickname.clone())),
+                    Ok(json) => Some((*key, json, value.nickname.clone())),
                     Err(_) => {
                         tracing::warn!("json error");
                         None
@@ -122,11 +476,12 @@ impl Galaxy {
             )
             .collect();
 
+        let connection = connected(&mut self.connection)?;
         jsons.iter().for_each(|(uuid, json, nickname)| {
             let key_name = format!("{}:player:{}", self.db_name, uuid);
-            let _: RedisResult<()> = self.connection.set(key_name.clone(), json);
+            let _: RedisResult<()> = connection.set(key_name.clone(), json);
 
-            let _: RedisResult<()> = self.connection.set(
+            let _: RedisResult<()> = connection.set(
                 format!("{}:nickname_to_uuid:{}", self.db_name, nickname),
                 uuid.to_string(),
             );
@@ -135,19 +490,39 @@ impl Galaxy {
         Ok(())
     }
 
+    pub fn load_players(&mut self) -> Result<()> {
+        let keys: Vec<String> = connected(&mut self.connection)?
+            .keys(format!("{}:player:*", self.db_name))
+            .map_err(|_| Error::KeysQueryError)?;
+
+        let re = Regex::new(r"^.*player:(.*)$").unwrap();
+        for key in keys {
+            let Some(uuid) = re
+                .captures(&key)
+                .and_then(|captures| Uuid::from_str(&captures[1]).ok())
+            else {
+                continue;
+            };
+            let json: String = self
+                .connection()?
+                .get(&key)
+                .map_err(|_| Error::NoPlayerForUuid)?;
+            let player = serde_json::from_str(&json).map_err(Error::PlayerDeserializationError)?;
+            self.players.insert(uuid, player);
+        }
+        Ok(())
+    }
+
     pub fn load_player_by_nickname(&mut self, nickname: String) -> Result<Uuid> {
-        let player_uuid: String = self
-            .connection
+        let player_uuid: String = connected(&mut self.connection)?
             .get(format!("{}:nickname_to_uuid:{}", self.db_name, nickname))
             .map_err(|_| Error::NoPlayerForNickname)?;
 
-        let json: String = self
-            .connection
+        let json: String = connected(&mut self.connection)?
             .get(format!("{}:player:{}", self.db_name, player_uuid))
             .map_err(|_| Error::NoPlayerForUuid)?;
 
-        let player =
-            serde_json::from_str(&json).map_err(|err| Error::PlayerDeserializationError(err))?;
+        let player = serde_json::from_str(&json).map_err(Error::PlayerDeserializationError)?;
 
         let uuid: Uuid = Uuid::from_str(player_uuid.as_str()).map_err(|_| Error::UuidError)?;
 
@@ -155,41 +530,1251 @@ impl Galaxy {
         Ok(uuid)
     }
 
+    pub fn start_hyperjump(&mut self, player_uuid: Uuid, target_system_uuid: Uuid) -> Result<f32> {
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+
+        if player.is_in_transit() {
+            return Err(Error::PlayerAlreadyInTransit);
+        }
+
+        if player.is_docked() {
+            return Err(Error::PlayerDocked);
+        }
+
+        if player.current_system_uuid == target_system_uuid {
+            return Err(Error::HyperjumpToCurrentSystem);
+        }
+
+        let origin_system_uuid = player.current_system_uuid;
+        let origin = self
+            .system_index
+            .get(&origin_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        let target = self
+            .system_index
+            .get(&target_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+
+        let distance = (target.galaxy_position() - origin.galaxy_position()).norm();
+        if distance > MAX_HYPERJUMP_RANGE {
+            return Err(Error::HyperjumpOutOfRange);
+        }
+
+        // Loaded now so that it is ready on arrival.
+        self.load_system(&target_system_uuid)?;
+
+        let hyperjump = Hyperjump::new(origin_system_uuid, target_system_uuid, distance);
+        let duration = hyperjump.duration;
+        if let Some(player) = self.players.get_mut(&player_uuid) {
+            player.hyperjump = Some(hyperjump);
+        }
+        self.publish(WorldEvent::HyperjumpStarted {
+            player_uuid,
+            origin_system_uuid,
+            target_system_uuid,
+        });
+
+        Ok(duration)
+    }
+
+    pub fn dock(&mut self, player_uuid: Uuid, station_uuid: Uuid) -> Result<()> {
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if player.is_in_transit() {
+            return Err(Error::PlayerInTransit);
+        }
+        if player.is_docked() {
+            return Err(Error::AlreadyDocked);
+        }
+        let system_uuid = player.current_system_uuid;
+        let system = self
+            .systems
+            .get_mut(&system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        docking::check_docking(system, &player_uuid, &station_uuid)?;
+        system.attach(&player_uuid, &station_uuid);
+        player.docked_station_uuid = Some(station_uuid);
+        self.system_cache.mark_dirty(system_uuid);
+        self.publish(WorldEvent::Docked {
+            player_uuid,
+            station_uuid,
+            system_uuid,
+        });
+        Ok(())
+    }
+
+    pub fn undock(&mut self, player_uuid: Uuid) -> Result<()> {
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        let station_uuid = player.docked_station_uuid.ok_or(Error::NotDocked)?;
+        let system_uuid = player.current_system_uuid;
+        if let Some(system) = self.systems.get_mut(&system_uuid) {
+            system.detach(&player_uuid);
+            self.system_cache.mark_dirty(system_uuid);
+        }
+        player.docked_station_uuid = None;
+        self.publish(WorldEvent::Undocked {
+            player_uuid,
+            station_uuid,
+            system_uuid,
+        });
+        Ok(())
+    }
+
+    pub fn use_station_service(
+        &mut self,
+        player_uuid: Uuid,
+        service: StationService,
+    ) -> Result<()> {
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if !player.is_docked() {
+            return Err(Error::NotDocked);
+        }
+        match service {
+            StationService::Repair => {
+                player.health = MAX_PLAYER_HEALTH;
+                if let Some(system) = self.systems.get_mut(&player.current_system_uuid) {
+                    system
+                        .entities
+                        .healths
+                        .insert(player_uuid, MAX_PLAYER_HEALTH);
+                    self.system_cache.mark_dirty(player.current_system_uuid);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn mine(&mut self, player_uuid: Uuid, request: MiningRequest) -> Result<MiningResult> {
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if player.is_in_transit() {
+            return Err(Error::PlayerInTransit);
+        }
+        // Cooldowns run on the clock, which doesn't move while paused.
+        if self.clock.paused {
+            return Err(Error::SimulationPaused);
+        }
+        if self
+            .mining_cooldowns
+            .get(&player_uuid)
+            .is_some_and(|ready_time| self.clock.time < *ready_time)
+        {
+            return Err(Error::MiningCooldown);
+        }
+        let system_uuid = player.current_system_uuid;
+        let system = self
+            .systems
+            .get_mut(&system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        if !system.entities.deposits.contains_key(&request.body_uuid) {
+            return Err(Error::NotMinable);
+        }
+        let player_coords = system
+            .absolute_coords(&player_uuid)
+            .unwrap_or(player.coords);
+        let body_coords = system
+            .absolute_coords(&request.body_uuid)
+            .ok_or(Error::NoBodyForUuid)?;
+        let radius = system.body_radius(&request.body_uuid).unwrap_or_default();
+        if (player_coords - body_coords).norm() - radius > MAX_MINING_DISTANCE {
+            return Err(Error::OutOfMiningRange);
+        }
+
+        let deposit = system
+            .entities
+            .deposits
+            .get_mut(&request.body_uuid)
+            .and_then(|deposits| {
+                deposits.iter_mut().find(|deposit| {
+                    deposit.amount >= 1.
+                        && request
+                            .resource
+                            .is_none_or(|resource| resource == deposit.resource)
+                })
+            })
+            .ok_or(Error::DepositDepleted)?;
+        let amount = deposit.amount.min(MINING_YIELD).floor();
+        let item = self.catalog.resource_item(deposit.resource)?;
+        player.inventory.add(&self.catalog, item, amount as u32)?;
+        deposit.amount -= amount;
+        let result = MiningResult {
+            body_uuid: request.body_uuid,
+            resource: deposit.resource,
+            amount,
+            remaining: deposit.amount,
+        };
+        self.mining_cooldowns
+            .insert(player_uuid, self.clock.time + MINING_COOLDOWN);
+        self.system_cache.mark_dirty(system_uuid);
+        Ok(result)
+    }
+
+    fn structure_access(&self, player_uuid: &Uuid, structure_uuid: &Uuid) -> Result<f32> {
+        let player = self
+            .players
+            .get(player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if player.is_in_transit() {
+            return Err(Error::PlayerInTransit);
+        }
+        let system = self
+            .systems
+            .get(&player.current_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        let structure = system
+            .entities
+            .structures
+            .get(structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        if system.entities.owners.get(structure_uuid) != Some(player_uuid) {
+            return Err(Error::NotStructureOwner);
+        }
+        let player_coords = system.absolute_coords(player_uuid).unwrap_or(player.coords);
+        let structure_coords = system
+            .absolute_coords(structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        Ok((structure_coords - player_coords).norm() - structure.bounding_radius())
+    }
+
+    pub fn query_inventory(
+        &self,
+        player_uuid: Uuid,
+        inventory_ref: InventoryRef,
+    ) -> Result<Inventory> {
+        let player = self
+            .players
+            .get(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        match inventory_ref {
+            InventoryRef::Player => Ok(player.inventory.clone()),
+            InventoryRef::Structure(structure_uuid) => {
+                self.structure_access(&player_uuid, &structure_uuid)?;
+                self.systems
+                    .get(&player.current_system_uuid)
+                    .ok_or(Error::NoSystemForUuid)?
+                    .cargo(&structure_uuid)
+            }
+        }
+    }
+
+    pub fn move_items(&mut self, player_uuid: Uuid, transfer: ItemTransfer) -> Result<()> {
+        for inventory_ref in [transfer.from, transfer.to] {
+            if let InventoryRef::Structure(structure_uuid) = inventory_ref {
+                if self.structure_access(&player_uuid, &structure_uuid)? > MAX_TRANSFER_DISTANCE {
+                    return Err(Error::OutOfTransferRange);
+                }
+            }
+        }
+        if transfer.from == transfer.to || transfer.quantity == 0 {
+            return Ok(());
+        }
+        let source = self.query_inventory(player_uuid, transfer.from)?;
+        if source.count(&transfer.item) < transfer.quantity {
+            return Err(Error::NotEnoughItems);
+        }
+        self.query_inventory(player_uuid, transfer.to)?.check_add(
+            &self.catalog,
+            &transfer.item,
+            transfer.quantity,
+        )?;
+
+        inventory_mut(
+            &mut self.players,
+            &mut self.systems,
+            &player_uuid,
+            transfer.from,
+        )?
+        .remove(&transfer.item, transfer.quantity)?;
+        inventory_mut(
+            &mut self.players,
+            &mut self.systems,
+            &player_uuid,
+            transfer.to,
+        )?
+        .insert(&self.catalog, &transfer.item, transfer.quantity)?;
+        if let Some(player) = self.players.get(&player_uuid) {
+            self.system_cache.mark_dirty(player.current_system_uuid);
+        }
+        Ok(())
+    }
+
+    fn production_access(&self, player_uuid: &Uuid, facility_uuid: &Uuid) -> Result<()> {
+        let player = self
+            .players
+            .get(player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        let system = self
+            .systems
+            .get(&player.current_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        if system.entities.body_types.get(facility_uuid) == Some(&BodyType::Station) {
+            if player.docked_station_uuid != Some(*facility_uuid) {
+                return Err(Error::NotDocked);
+            }
+            return Ok(());
+        }
+        if !system.entities.structures.contains_key(facility_uuid) {
+            return Err(Error::NotAProductionFacility);
+        }
+        if self.structure_access(player_uuid, facility_uuid)? > MAX_TRANSFER_DISTANCE {
+            return Err(Error::OutOfTransferRange);
+        }
+        Ok(())
+    }
+
+    pub fn order_production(&mut self, player_uuid: Uuid, order: ProductionOrder) -> Result<()> {
+        if order.runs == 0 {
+            return Err(Error::NoProductionRuns);
+        }
+        let recipe = self
+            .recipes
+            .get(&order.recipe)
+            .ok_or_else(|| Error::UnknownRecipe(order.recipe.clone()))?;
+        self.production_access(&player_uuid, &order.facility_uuid)?;
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        let system_uuid = player.current_system_uuid;
+        let system = self
+            .systems
+            .get_mut(&system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        if production_slots(&system.entities, &order.facility_uuid, recipe.facility) == 0 {
+            return Err(Error::MissingProductionBlocks);
+        }
+        let queue = system
+            .entities
+            .productions
+            .entry(order.facility_uuid)
+            .or_default();
+        let queued = queue
+            .iter()
+            .filter(|job| job.owner_uuid == player_uuid)
+            .count();
+        if queued + order.runs as usize > MAX_PRODUCTION_QUEUE {
+            return Err(Error::ProductionQueueFull);
+        }
+        if recipe.inputs.iter().any(|input| {
+            player.inventory.count(&input.item) < input.quantity.saturating_mul(order.runs)
+        }) {
+            return Err(Error::NotEnoughItems);
+        }
+        for input in recipe.inputs.iter() {
+            player
+                .inventory
+                .remove(&input.item, input.quantity * order.runs)?;
+        }
+        queue.extend((0..order.runs).map(|_| ProductionJob::new(player_uuid, recipe)));
+        self.system_cache.mark_dirty(system_uuid);
+        Ok(())
+    }
+
+    pub fn production_queue(
+        &self,
+        player_uuid: Uuid,
+        facility_uuid: Uuid,
+    ) -> Result<Vec<ProductionJob>> {
+        self.production_access(&player_uuid, &facility_uuid)?;
+        let player = self
+            .players
+            .get(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        let system = self
+            .systems
+            .get(&player.current_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        Ok(system
+            .entities
+            .productions
+            .get(&facility_uuid)
+            .into_iter()
+            .flatten()
+            .filter(|job| job.owner_uuid == player_uuid)
+            .cloned()
+            .collect())
+    }
+
+    pub fn collect_production(
+        &mut self,
+        player_uuid: Uuid,
+        facility_uuid: Uuid,
+    ) -> Result<Vec<ItemStack>> {
+        self.production_access(&player_uuid, &facility_uuid)?;
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        let system_uuid = player.current_system_uuid;
+        let system = self
+            .systems
+            .get_mut(&system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        let jobs = system
+            .entities
+            .productions
+            .get_mut(&facility_uuid)
+            .ok_or(Error::NothingToCollect)?;
+
+        let mut collected = Vec::new();
+        let mut full = false;
+        jobs.retain(|job| {
+            if full || job.owner_uuid != player_uuid || !job.is_finished() {
+                return true;
+            }
+            let mut inventory = player.inventory.clone();
+            for output in job.outputs.iter() {
+                if inventory
+                    .add(&self.catalog, &output.item, output.quantity)
+                    .is_err()
+                {
+                    full = true;
+                    return true;
+                }
+            }
+            player.inventory = inventory;
+            collected.extend(job.outputs.iter().cloned());
+            false
+        });
+        if jobs.is_empty() {
+            system.entities.productions.remove(&facility_uuid);
+        }
+        if collected.is_empty() {
+            return Err(if full {
+                Error::InventoryFull
+            } else {
+                Error::NothingToCollect
+            });
+        }
+        self.system_cache.mark_dirty(system_uuid);
+        Ok(collected)
+    }
+
+    pub fn set_flight_controls(&mut self, player_uuid: Uuid, input: FlightInput) -> Result<()> {
+        let player = self
+            .players
+            .get(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if player.is_in_transit() {
+            return Err(Error::PlayerInTransit);
+        }
+        let system = self
+            .systems
+            .get_mut(&player.current_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        if !system
+            .entities
+            .structures
+            .contains_key(&input.structure_uuid)
+        {
+            return Err(Error::NoStructureForUuid);
+        }
+        if system.entities.owners.get(&input.structure_uuid) != Some(&player_uuid) {
+            return Err(Error::NotStructureOwner);
+        }
+        system
+            .entities
+            .flight_controls
+            .insert(input.structure_uuid, input.controls.clamped());
+        Ok(())
+    }
+
+    pub fn edit_block(
+        &mut self,
+        player_uuid: Uuid,
+        structure_uuid: Uuid,
+        position: Vector3<BlockCoordsRepr>,
+        edit: BlockEdit,
+    ) -> Result<StructureChange> {
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if player.is_in_transit() {
+            return Err(Error::PlayerInTransit);
+        }
+        let system_uuid = player.current_system_uuid;
+        let system = self
+            .systems
+            .get_mut(&system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        let structure = system
+            .entities
+            .structures
+            .get(&structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        if system.entities.owners.get(&structure_uuid) != Some(&player_uuid) {
+            return Err(Error::NotStructureOwner);
+        }
+
+        let player_coords = system
+            .absolute_coords(&player_uuid)
+            .unwrap_or(player.coords);
+        let structure_coords = system
+            .absolute_coords(&structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        let orientation = system
+            .entities
+            .orientations
+            .get(&structure_uuid)
+            .copied()
+            .unwrap_or_default();
+        let block_coords =
+            structure_coords + orientation * position.map(|coord| coord as f32) * BLOCK_SIZE;
+        if (block_coords - player_coords).norm() > MAX_BUILD_RANGE {
+            return Err(Error::OutOfBuildRange);
+        }
+
+        let current = structure.block(&position);
+        let block_type = match edit {
+            BlockEdit::Place(block_type) => {
+                if current.is_some() {
+                    return Err(Error::BlockPositionOccupied);
+                }
+                if !structure.is_adjacent(&position) {
+                    return Err(Error::BlockNotAdjacent);
+                }
+                Some(block_type)
+            }
+            BlockEdit::Remove => {
+                current.ok_or(Error::NoBlockAtPosition)?;
+                if !structure.stays_connected_without(&position) {
+                    return Err(Error::StructureWouldSplit);
+                }
+                None
+            }
+            BlockEdit::Replace(block_type) => {
+                current.ok_or(Error::NoBlockAtPosition)?;
+                Some(block_type)
+            }
+        };
+
+        // Cargo blocks can't be taken out from under their cargo, nor can the
+        // last block of a structure carrying anything.
+        let cargo = system.cargo(&structure_uuid)?;
+        if !cargo.is_empty() {
+            let (removed_mass, removed_volume) =
+                current.map_or((0., 0.), |previous| previous.cargo_capacity());
+            let (added_mass, added_volume) =
+                block_type.map_or((0., 0.), |block_type| block_type.cargo_capacity());
+            let emptied = block_type.is_none() && structure.len() == 1;
+            if emptied
+                || cargo.mass(&self.catalog) > cargo.max_mass - removed_mass + added_mass
+                || cargo.volume(&self.catalog) > cargo.max_volume - removed_volume + added_volume
+            {
+                return Err(Error::CargoWouldNotFit);
+            }
+        }
+
+        // Removed blocks go back to the inventory, which must have room.
+        let refund = current
+            .map(|previous| self.catalog.block_item(previous))
+            .transpose()?;
+        if let Some(item) = refund {
+            player.inventory.check_add(&self.catalog, item, 1)?;
+        }
+        if let Some(block_type) = block_type {
+            let item = self.catalog.block_item(block_type)?;
+            player
+                .inventory
+                .remove(item, 1)
+                .map_err(|_| Error::NotEnoughBlocks)?;
+        }
+        system.set_block(&structure_uuid, position, block_type)?;
+        if let Some(item) = refund {
+            player.inventory.insert(&self.catalog, item, 1)?;
+        }
+        // Anything would be adjacent to an empty structure.
+        let emptied = system
+            .entities
+            .structures
+            .get(&structure_uuid)
+            .is_some_and(Structure::is_empty);
+        if emptied {
+            system.entities.despawn(&structure_uuid);
+        }
+        self.system_cache.mark_dirty(system_uuid);
+        if emptied {
+            self.publish(WorldEvent::EntityDestroyed {
+                entity_uuid: structure_uuid,
+                system_uuid,
+            });
+        }
+
+        Ok(StructureChange {
+            system_uuid,
+            structure_uuid,
+            position,
+            block_type,
+        })
+    }
+
+    fn blueprint_key(&self, player_uuid: &Uuid, name: &str, version: u32) -> String {
+        format!(
+            "{}:blueprint:{}:{}:{}",
+            self.db_name, player_uuid, name, version
+        )
+    }
+
+    fn blueprint_index_key(&self, player_uuid: &Uuid) -> String {
+        format!("{}:blueprints:{}", self.db_name, player_uuid)
+    }
+
+    fn blueprint_counter_key(&self, player_uuid: &Uuid) -> String {
+        format!("{}:blueprint_versions:{}", self.db_name, player_uuid)
+    }
+
+    fn blueprint_index(&mut self, player_uuid: &Uuid) -> Result<Vec<(String, u32)>> {
+        let index_key = self.blueprint_index_key(player_uuid);
+        let members: Vec<String> = connected(&mut self.connection)?
+            .smembers(index_key)
+            .map_err(Error::LoadBlueprintError)?;
+        let mut index: Vec<(String, u32)> = members
+            .iter()
+            .filter_map(|member| {
+                let (name, version) = member.rsplit_once(':')?;
+                Some((name.to_string(), version.parse().ok()?))
+            })
+            .collect();
+        index.sort();
+        Ok(index)
+    }
+
+    fn blueprint_versions(&mut self, player_uuid: &Uuid, name: &str) -> Result<Vec<u32>> {
+        Ok(self
+            .blueprint_index(player_uuid)?
+            .into_iter()
+            .filter(|(indexed_name, _version)| indexed_name == name)
+            .map(|(_name, version)| version)
+            .collect())
+    }
+
+    fn add_blueprint(
+        &mut self,
+        player_uuid: Uuid,
+        name: String,
+        structure: Structure,
+    ) -> Result<BlueprintSummary> {
+        if !blueprint::is_valid_name(&name) {
+            return Err(Error::InvalidBlueprintName);
+        }
+        if structure.is_empty() {
+            return Err(Error::EmptyBlueprint);
+        }
+        if structure.len() > MAX_BLUEPRINT_BLOCKS {
+            return Err(Error::BlueprintTooLarge);
+        }
+        if !structure.is_connected() {
+            return Err(Error::BlueprintNotConnected);
+        }
+        // Spawning is paid for from the player's cargo in one go.
+        let mut cost = Inventory::player();
+        for (_position, block_type) in structure.blocks() {
+            cost.add(&self.catalog, self.catalog.block_item(*block_type)?, 1)
+                .map_err(|_| Error::BlueprintCostTooLarge)?;
+        }
+        let counter_key = self.blueprint_counter_key(&player_uuid);
+        let version: u32 = connected(&mut self.connection)?
+            .hincr(counter_key, &name, 1)
+            .map_err(Error::SaveBlueprintError)?;
+        let blueprint = Blueprint {
+            name,
+            version,
+            owner_uuid: player_uuid,
+            structure,
+        };
+        let json = serde_json::to_string(&blueprint).map_err(Error::BlueprintSerializationError)?;
+        let key = self.blueprint_key(&player_uuid, &blueprint.name, version);
+        let index_key = self.blueprint_index_key(&player_uuid);
+        let connection = connected(&mut self.connection)?;
+        connection
+            .set::<_, _, ()>(key, json)
+            .map_err(Error::SaveBlueprintError)?;
+        connection
+            .sadd::<_, _, ()>(index_key, format!("{}:{}", blueprint.name, version))
+            .map_err(Error::SaveBlueprintError)?;
+        Ok(blueprint.summary())
+    }
+
+    pub fn capture_blueprint(
+        &mut self,
+        player_uuid: Uuid,
+        structure_uuid: Uuid,
+        name: String,
+    ) -> Result<BlueprintSummary> {
+        let player = self
+            .players
+            .get(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if player.is_in_transit() {
+            return Err(Error::PlayerInTransit);
+        }
+        let system = self
+            .systems
+            .get(&player.current_system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        let structure = system
+            .entities
+            .structures
+            .get(&structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        if system.entities.owners.get(&structure_uuid) != Some(&player_uuid) {
+            return Err(Error::NotStructureOwner);
+        }
+        let structure = structure.clone();
+        self.add_blueprint(player_uuid, name, structure)
+    }
+
+    pub fn import_blueprint(
+        &mut self,
+        player_uuid: Uuid,
+        blueprint: Blueprint,
+    ) -> Result<BlueprintSummary> {
+        if !self.players.contains_key(&player_uuid) {
+            return Err(Error::NoPlayerForUuid);
+        }
+        self.add_blueprint(player_uuid, blueprint.name, blueprint.structure)
+    }
+
+    pub fn load_blueprint(
+        &mut self,
+        player_uuid: Uuid,
+        blueprint_ref: &BlueprintRef,
+    ) -> Result<Blueprint> {
+        if !blueprint::is_valid_name(&blueprint_ref.name) {
+            return Err(Error::InvalidBlueprintName);
+        }
+        let version = match blueprint_ref.version {
+            Some(version) => version,
+            None => *self
+                .blueprint_versions(&player_uuid, &blueprint_ref.name)?
+                .last()
+                .ok_or(Error::NoBlueprintForName)?,
+        };
+        let key = self.blueprint_key(&player_uuid, &blueprint_ref.name, version);
+        let json: Option<String> = connected(&mut self.connection)?
+            .get(key)
+            .map_err(Error::LoadBlueprintError)?;
+        let json = json.ok_or(Error::NoBlueprintForName)?;
+        serde_json::from_str(&json).map_err(Error::BlueprintDeserializationError)
+    }
+
+    pub fn list_blueprints(&mut self, player_uuid: Uuid) -> Result<Vec<BlueprintSummary>> {
+        let keys: Vec<String> = self
+            .blueprint_index(&player_uuid)?
+            .iter()
+            .map(|(name, version)| self.blueprint_key(&player_uuid, name, *version))
+            .collect();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let jsons: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(keys)
+            .query(connected(&mut self.connection)?)
+            .map_err(Error::LoadBlueprintError)?;
+        let mut summaries = Vec::new();
+        for json in jsons.into_iter().flatten() {
+            let blueprint: Blueprint =
+                serde_json::from_str(&json).map_err(Error::BlueprintDeserializationError)?;
+            summaries.push(blueprint.summary());
+        }
+        summaries.sort_by(|a, b| a.name.cmp(&b.name).then(a.version.cmp(&b.version)));
+        Ok(summaries)
+    }
+
+    pub fn delete_blueprint(
+        &mut self,
+        player_uuid: Uuid,
+        blueprint_ref: &BlueprintRef,
+    ) -> Result<usize> {
+        if !blueprint::is_valid_name(&blueprint_ref.name) {
+            return Err(Error::InvalidBlueprintName);
+        }
+        let versions: Vec<u32> = self
+            .blueprint_versions(&player_uuid, &blueprint_ref.name)?
+            .into_iter()
+            .filter(|version| {
+                blueprint_ref
+                    .version
+                    .is_none_or(|wanted| wanted == *version)
+            })
+            .collect();
+        if versions.is_empty() {
+            return Err(Error::NoBlueprintForName);
+        }
+        let index_key = self.blueprint_index_key(&player_uuid);
+        for version in versions.iter() {
+            let key = self.blueprint_key(&player_uuid, &blueprint_ref.name, *version);
+            let connection = connected(&mut self.connection)?;
+            connection
+                .del::<_, ()>(key)
+                .map_err(|_| Error::DeletionQueryError)?;
+            connection
+                .srem::<_, _, ()>(&index_key, format!("{}:{}", blueprint_ref.name, version))
+                .map_err(|_| Error::DeletionQueryError)?;
+        }
+        Ok(versions.len())
+    }
+
+    pub fn spawn_blueprint(
+        &mut self,
+        player_uuid: Uuid,
+        blueprint_ref: &BlueprintRef,
+        position: Vector3<f32>,
+    ) -> Result<Uuid> {
+        let blueprint = self.load_blueprint(player_uuid, blueprint_ref)?;
+        let player = self
+            .players
+            .get_mut(&player_uuid)
+            .ok_or(Error::NoPlayerForUuid)?;
+        if player.is_in_transit() {
+            return Err(Error::PlayerInTransit);
+        }
+        let system_uuid = player.current_system_uuid;
+        let system = self
+            .systems
+            .get_mut(&system_uuid)
+            .ok_or(Error::NoSystemForUuid)?;
+        let player_coords = system
+            .absolute_coords(&player_uuid)
+            .unwrap_or(player.coords);
+        if (position - player_coords).norm() > MAX_BUILD_RANGE {
+            return Err(Error::OutOfBuildRange);
+        }
+        if collision::overlaps(system, &position, &blueprint.structure) {
+            return Err(Error::SpawnPositionOccupied);
+        }
+
+        let mut cost = Vec::new();
+        for (block_type, count) in blueprint.cost() {
+            let item = self.catalog.block_item(block_type)?;
+            if player.inventory.count(item) < count {
+                return Err(Error::NotEnoughBlocks);
+            }
+            cost.push((item, count));
+        }
+        for (item, count) in cost {
+            player.inventory.remove(item, count)?;
+        }
+        let uuid = system.spawn_structure(player_uuid, position, blueprint.structure);
+        self.system_cache.mark_dirty(system_uuid);
+        Ok(uuid)
+    }
+
+    pub fn find_route(&self, from: Uuid, to: Uuid) -> Result<JumpRoute> {
+        if self.system_index.get(&from).is_none() || self.system_index.get(&to).is_none() {
+            return Err(Error::NoSystemForUuid);
+        }
+        route::find_route(&self.system_index, from, to, MAX_HYPERJUMP_RANGE)
+            .ok_or(Error::NoRouteFound)
+    }
+
+    pub fn observed_systems(&self) -> HashSet<Uuid> {
+        self.players
+            .values()
+            .filter(|player| !player.is_in_transit())
+            .map(|player| player.current_system_uuid)
+            .collect()
+    }
+
+    pub fn pinned_systems(&self) -> HashSet<Uuid> {
+        let mut pinned = self.observed_systems();
+        pinned.extend(
+            self.players
+                .values()
+                .filter_map(|player| player.hyperjump.as_ref())
+                .map(|hyperjump| hyperjump.target_system_uuid),
+        );
+        pinned
+    }
+
+    pub fn evict_systems(&mut self, now: Instant) -> Result<usize> {
+        // Evicted systems are saved to be loaded back later, which offline
+        // and read-only galaxies can't do.
+        if self.connection.is_none() || self.read_only {
+            return Ok(0);
+        }
+        let pinned = self.pinned_systems();
+        for uuid in &pinned {
+            self.system_cache.touch(*uuid, now);
+        }
+
+        let evicted = self
+            .system_cache
+            .eviction_candidates(self.systems.keys(), &pinned, now);
+        for uuid in &evicted {
+            if self.system_cache.is_dirty(uuid) {
+                self.save_system(uuid)?;
+            }
+            self.systems.remove(uuid);
+            self.system_cache.forget(uuid);
+        }
+
+        if !evicted.is_empty() {
+            // Evicted systems are ahead of the saved clock, it is saved along
+            // so that a restart doesn't rewind the galaxy behind them.
+            self.save_clock()?;
+            tracing::debug!("{} systems were evicted", evicted.len());
+        }
+        Ok(evicted.len())
+    }
+
+    pub fn place_players(&mut self) {
+        let mut events = Vec::new();
+        for (system_uuid, system) in self.systems.iter_mut() {
+            let stale: Vec<Uuid> = system
+                .entities
+                .players
+                .iter()
+                .filter(|uuid| {
+                    self.players.get(uuid).is_none_or(|player| {
+                        player.is_in_transit() || player.current_system_uuid != *system_uuid
+                    })
+                })
+                .copied()
+                .collect();
+            for uuid in stale {
+                system.entities.despawn(&uuid);
+                // Structures don't keep flying without their pilot.
+                let piloted: Vec<Uuid> = system.owned_structures(&uuid).collect();
+                for structure_uuid in piloted {
+                    system.entities.flight_controls.remove(&structure_uuid);
+                }
+                self.system_cache.mark_dirty(*system_uuid);
+                events.push(WorldEvent::LeftSystem {
+                    player_uuid: uuid,
+                    system_uuid: *system_uuid,
+                });
+            }
+        }
+
+        for (uuid, player) in self.players.iter() {
+            if player.is_in_transit() {
+                continue;
+            }
+            let Some(system) = self.systems.get_mut(&player.current_system_uuid) else {
+                continue;
+            };
+            if !system.entities.players.contains(uuid) {
+                system.spawn_player(*uuid, player);
+                self.system_cache.mark_dirty(player.current_system_uuid);
+                events.push(WorldEvent::EnteredSystem {
+                    player_uuid: *uuid,
+                    system_uuid: player.current_system_uuid,
+                });
+            }
+        }
+
+        for event in events {
+            self.publish(event);
+        }
+    }
+
+    pub fn sync_players(&mut self) {
+        for (uuid, player) in self.players.iter_mut() {
+            if player.is_in_transit() {
+                continue;
+            }
+            if let Some(system) = self.systems.get(&player.current_system_uuid) {
+                system.sync_player(uuid, player);
+            }
+        }
+    }
+
+    pub fn apply_hazards(&mut self, delta: f32) -> Result<()> {
+        for player in self.players.values_mut() {
+            player.hazard = HazardReport::default();
+        }
+
+        let mut killed = Vec::new();
+        for system_uuid in self.observed_systems() {
+            let Some(system) = self.systems.get_mut(&system_uuid) else {
+                continue;
+            };
+            let reports: Vec<(Uuid, HazardReport)> = system
+                .entities
+                .healths
+                .keys()
+                .filter_map(|uuid| {
+                    let coords = system.absolute_coords(uuid)?;
+                    Some((*uuid, hazard::hazard_at(system, &coords)))
+                })
+                .collect();
+
+            for (uuid, report) in reports {
+                if let Some(player) = self.players.get_mut(&uuid) {
+                    player.hazard = report;
+                }
+                let Some(health) = system.entities.healths.get_mut(&uuid) else {
+                    continue;
+                };
+                *health -= report.damage_per_second() * delta;
+                if *health <= 0. {
+                    killed.push((system_uuid, uuid));
+                }
+            }
+        }
+
+        for (system_uuid, uuid) in killed {
+            if let Some(system) = self.systems.get_mut(&system_uuid) {
+                system.entities.despawn(&uuid);
+            }
+            let Some(own_system_uuid) =
+                self.players.get(&uuid).map(|player| player.own_system_uuid)
+            else {
+                self.publish(WorldEvent::EntityDestroyed {
+                    entity_uuid: uuid,
+                    system_uuid,
+                });
+                continue;
+            };
+            self.publish(WorldEvent::PlayerKilled {
+                player_uuid: uuid,
+                system_uuid,
+            });
+            tracing::info!(
+                "{} was killed by hazards, respawning",
+                self.players[&uuid].nickname
+            );
+            if let Err(err) = self.load_system(&own_system_uuid) {
+                tracing::warn!("Could not load the home system {own_system_uuid}: {err}");
+            }
+            let coords = match self.systems.get(&own_system_uuid) {
+                Some(own_system) => hazard::spawn_coords(own_system, PLAYER_SPAWN_COORDS),
+                None => PLAYER_SPAWN_COORDS,
+            };
+            if let Some(player) = self.players.get_mut(&uuid) {
+                player.respawn(coords);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn publish_collisions(&mut self) {
+        let mut events = Vec::new();
+        for (system_uuid, system) in self.systems.iter_mut() {
+            for contact in system.collisions.drain(..) {
+                events.push(WorldEvent::Collision {
+                    system_uuid: *system_uuid,
+                    first_uuid: contact.first_uuid,
+                    second_uuid: contact.second_uuid,
+                });
+            }
+        }
+        for event in events {
+            self.publish(event);
+        }
+    }
+
+    pub fn publish_finished_production(&mut self) {
+        let mut events = Vec::new();
+        for (system_uuid, system) in self.systems.iter_mut() {
+            for (facility_uuid, job) in system.finished_jobs.drain(..) {
+                events.push(WorldEvent::ProductionFinished {
+                    owner_uuid: job.owner_uuid,
+                    facility_uuid,
+                    recipe: job.recipe,
+                    system_uuid: *system_uuid,
+                });
+            }
+        }
+        for event in events {
+            self.publish(event);
+        }
+    }
+
+    pub fn save_clock(&mut self) -> Result<()> {
+        let json = serde_json::to_string(&self.clock).map_err(Error::ClockSerializationError)?;
+        connected(&mut self.connection)?
+            .set::<_, _, ()>(format!("{}:clock", self.db_name), json)
+            .map_err(Error::SaveClockSetError)
+    }
+
+    pub fn load_clock(&mut self) -> Result<()> {
+        let res: RedisResult<Option<String>> =
+            connected(&mut self.connection)?.get(format!("{}:clock", self.db_name));
+        let Ok(Some(json)) = res else {
+            self.clock.time = self.clock.time.max(self.stored_systems_time()?);
+            if !self.read_only {
+                self.save_clock()?;
+            }
+            return Ok(());
+        };
+        let clock: SimulationClock =
+            serde_json::from_str(&json).map_err(Error::ClockDeserializationError)?;
+        let time = self.clock.time.max(clock.time);
+        self.clock = SimulationClock { time, ..clock };
+        Ok(())
+    }
+
+    fn stored_systems_time(&mut self) -> Result<f64> {
+        #[derive(Deserialize)]
+        struct SimulatedTime {
+            #[serde(default)]
+            simulated_time: f64,
+        }
+
+        let keys: Vec<String> = connected(&mut self.connection)?
+            .keys(format!("{}:system:*", self.db_name))
+            .map_err(|_| Error::KeysQueryError)?;
+        let mut time: f64 = 0.;
+        for key in keys {
+            let res: RedisResult<String> = connected(&mut self.connection)?.get(&key);
+            match res.map(|json| serde_json::from_str::<SimulatedTime>(&json)) {
+                Ok(Ok(system)) => time = time.max(system.simulated_time),
+                _ => tracing::warn!("Could not read the time of system {key}"),
+            }
+        }
+        Ok(time)
+    }
+
+    pub fn save_dirty_systems(&mut self) -> Result<()> {
+        let uuids: Vec<Uuid> = self
+            .systems
+            .keys()
+            .filter(|uuid| self.system_cache.is_dirty(uuid))
+            .copied()
+            .collect();
+        for uuid in uuids {
+            self.save_system(&uuid)?;
+        }
+        Ok(())
+    }
+
     pub fn load_all(&mut self) -> Result<()> {
-        self.load_systems()?;
+        self.load_system_index()?;
+        self.load_clock()?;
         Ok(())
     }
 
     pub fn save_all(&mut self) -> Result<()> {
-        self.save_systems()?;
+        self.save_dirty_systems()?;
+        self.save_players()?;
+        self.save_clock()?;
         Ok(())
     }
 
+    pub fn tick(&mut self, wall_delta: f32) -> Result<()> {
+        let delta = self.clock.simulated_delta(wall_delta);
+        self.update(delta)
+    }
+
+    pub fn simulate(&mut self, duration: f64, step: f64) -> Result<()> {
+        let mut remaining = duration;
+        while remaining > 0. {
+            let delta = remaining.min(step);
+            self.update(delta as f32)?;
+            remaining -= delta;
+        }
+
+        let uuids: HashSet<Uuid> = self.systems.keys().copied().collect();
+        update_systems(
+            &self.thread_pool,
+            &mut self.systems,
+            &uuids,
+            self.clock.time,
+        )?;
+        for uuid in uuids {
+            self.system_cache.mark_dirty(uuid);
+        }
+
+        Ok(())
+    }
+
+    pub fn export(&self) -> GalaxyExport {
+        GalaxyExport {
+            clock: self.clock.clone(),
+            systems: self.systems.clone(),
+            players: self.players.clone(),
+        }
+    }
+
+    pub fn import(&mut self, export: GalaxyExport) {
+        for (uuid, system) in export.systems {
+            if self.insert_loaded_system(uuid, system) {
+                self.system_cache.mark_dirty(uuid);
+            }
+        }
+        self.players.extend(export.players);
+        let time = self.clock.time.max(export.clock.time);
+        self.clock = SimulationClock {
+            time,
+            ..export.clock
+        };
+    }
+
     pub fn clear_db(&mut self) -> Result<()> {
         let all_keys = self.all_keys()?;
 
         for key in all_keys {
-            self.connection
-                .del(key)
+            connected(&mut self.connection)?
+                .del::<_, ()>(key)
                 .map_err(|_| Error::DeletionQueryError)?;
         }
         Ok(())
     }
 
     fn all_keys(&mut self) -> Result<Vec<String>> {
-        Ok(self
-            .connection
+        connected(&mut self.connection)?
             .keys(format!("{}:*", self.db_name))
-            .map_err(|_| Error::KeysQueryError)?)
+            .map_err(|_| Error::KeysQueryError)
     }
 }
 
 impl Temporal for Galaxy {
     fn update(&mut self, delta: f32) -> Result<()> {
-        for (_uuid, system) in self.systems.iter_mut() {
-            system.update(delta)?;
+        self.clock.time += delta as f64;
+
+        for uuid in self.pinned_systems() {
+            if let Err(err) = self.load_system(&uuid) {
+                tracing::warn!("Could not load system {uuid}: {err}");
+            }
+        }
+
+        for (_uuid, player) in self.players.iter_mut() {
+            let was_in_transit = player.is_in_transit();
+            player.update(delta)?;
+            if was_in_transit && !player.is_in_transit() {
+                if let Some(system) = self.systems.get(&player.current_system_uuid) {
+                    player.coords = hazard::spawn_coords(system, PLAYER_SPAWN_COORDS);
+                }
+            }
         }
 
+        self.place_players();
+
+        let observed_systems = self.observed_systems();
+        update_systems(
+            &self.thread_pool,
+            &mut self.systems,
+            &observed_systems,
+            self.clock.time,
+        )?;
+        for uuid in observed_systems {
+            if self.systems.contains_key(&uuid) {
+                self.system_cache.mark_dirty(uuid);
+            }
+        }
+
+        self.publish_collisions();
+        self.publish_finished_production();
+        self.apply_hazards(delta)?;
+        self.sync_players();
+        self.dispatch_events();
+
         Ok(())
     }
 }
@@ -0,0 +1,132 @@
+use super::{
+    stellar::StellarProperties,
+    system::{CenterType, System},
+};
+use crate::SystemCoordsRepr;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+pub const GRAVITATIONAL_CONSTANT: f64 = 6.674e-11; // m³/(kg·s²)
+pub const SOLAR_MASS: f64 = 1.989e30; // kg
+
+pub const RADIATION_REFERENCE_DISTANCE: f64 = 1.0e9; // meter
+pub const RADIATION_DOSE_RATE_PER_SOLAR_LUMINOSITY: f64 = 1.; // gray/second at reference distance
+pub const BLACK_HOLE_ACCRETION_LUMINOSITY: f64 = 0.01; // solar luminosity per solar mass
+pub const RADIATION_THRESHOLD: f32 = 0.05; // gray/second
+pub const TIDAL_STRESS_THRESHOLD: f32 = 1.0e-4; // 1/second²
+
+pub const RADIATION_DAMAGE_PER_GRAY: f32 = 10.; // health
+pub const TIDAL_DAMAGE_RATE: f32 = 1.; // health/second at twice the threshold
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum HazardLevel {
+    #[default]
+    Safe,
+    Warning,
+    Danger,
+    Lethal,
+}
+
+impl HazardLevel {
+    fn from_ratio(ratio: f32) -> Self {
+        match ratio {
+            ratio if ratio < 1. => HazardLevel::Safe,
+            ratio if ratio < 10. => HazardLevel::Warning,
+            ratio if ratio < 100. => HazardLevel::Danger,
+            _ => HazardLevel::Lethal,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct HazardReport {
+    pub radiation: f32,    // gray/second
+    pub tidal_stress: f32, // 1/second²
+    pub level: HazardLevel,
+}
+
+impl HazardReport {
+    pub fn damage_per_second(&self) -> f32 {
+        let radiation = (self.radiation - RADIATION_THRESHOLD).max(0.) * RADIATION_DAMAGE_PER_GRAY;
+        let tidal = ((self.tidal_stress - TIDAL_STRESS_THRESHOLD) / TIDAL_STRESS_THRESHOLD).max(0.)
+            * TIDAL_DAMAGE_RATE;
+        radiation + tidal
+    }
+}
+
+fn hard_radiation_output(center_type: CenterType, star: &StellarProperties) -> f64 {
+    match center_type {
+        CenterType::NeutronStar => star.luminosity,
+        CenterType::BlackHole => star.mass * BLACK_HOLE_ACCRETION_LUMINOSITY,
+        CenterType::OneStar | CenterType::TwoStars | CenterType::ThreeStars => 0.,
+    }
+}
+
+fn is_exotic(center_type: CenterType) -> bool {
+    matches!(center_type, CenterType::BlackHole | CenterType::NeutronStar)
+}
+
+pub fn hazard_at(system: &System, position: &Vector3<SystemCoordsRepr>) -> HazardReport {
+    if !is_exotic(system.center_type) {
+        return HazardReport::default();
+    }
+
+    let distance = (position.map(|coord| coord as f64).norm()).max(1.);
+
+    let hard_output: f64 = system
+        .stellar_properties
+        .iter()
+        .map(|star| hard_radiation_output(system.center_type, star))
+        .sum();
+    let radiation = RADIATION_DOSE_RATE_PER_SOLAR_LUMINOSITY * hard_output
+        / (distance / RADIATION_REFERENCE_DISTANCE).powi(2);
+
+    let tidal_stress =
+        2. * GRAVITATIONAL_CONSTANT * system.total_mass() * SOLAR_MASS / distance.powi(3);
+
+    let radiation = radiation as f32;
+    let tidal_stress = tidal_stress as f32;
+    HazardReport {
+        radiation,
+        tidal_stress,
+        level: HazardLevel::from_ratio(radiation / RADIATION_THRESHOLD).max(
+            HazardLevel::from_ratio(tidal_stress / TIDAL_STRESS_THRESHOLD),
+        ),
+    }
+}
+
+pub fn safe_distance(system: &System) -> f64 {
+    if !is_exotic(system.center_type) {
+        return 0.;
+    }
+
+    let hard_output: f64 = system
+        .stellar_properties
+        .iter()
+        .map(|star| hard_radiation_output(system.center_type, star))
+        .sum();
+    let radiation_distance = RADIATION_REFERENCE_DISTANCE
+        * (RADIATION_DOSE_RATE_PER_SOLAR_LUMINOSITY * hard_output / RADIATION_THRESHOLD as f64)
+            .sqrt();
+    let tidal_distance = (2. * GRAVITATIONAL_CONSTANT * system.total_mass() * SOLAR_MASS
+        / TIDAL_STRESS_THRESHOLD as f64)
+        .cbrt();
+
+    radiation_distance.max(tidal_distance)
+}
+
+pub fn spawn_coords(
+    system: &System,
+    default: Vector3<SystemCoordsRepr>,
+) -> Vector3<SystemCoordsRepr> {
+    let min_distance = 2. * safe_distance(system);
+    let default_distance = default.map(|coord| coord as f64).norm();
+    if default_distance >= min_distance {
+        return default;
+    }
+    let direction = match default.try_normalize(f32::EPSILON) {
+        Some(direction) => direction,
+        None => Vector3::x(),
+    };
+    direction * min_distance as SystemCoordsRepr
+}
@@ -0,0 +1,46 @@
+use super::temporal::Temporal;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const HYPERJUMP_SPIN_UP_DURATION: f32 = 5.; // second
+pub const HYPERJUMP_SECONDS_PER_PARSEC: f32 = 0.01; // second
+pub const MAX_HYPERJUMP_RANGE: f64 = 1000.; // parsec
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Hyperjump {
+    pub origin_system_uuid: Uuid,
+    pub target_system_uuid: Uuid,
+    pub duration: f32, // second
+    pub elapsed: f32,  // second
+}
+
+impl Hyperjump {
+    pub fn new(origin_system_uuid: Uuid, target_system_uuid: Uuid, distance: f64) -> Self {
+        Self {
+            origin_system_uuid,
+            target_system_uuid,
+            duration: Self::duration_for_distance(distance),
+            elapsed: 0.,
+        }
+    }
+
+    pub fn duration_for_distance(distance: f64) -> f32 {
+        HYPERJUMP_SPIN_UP_DURATION + distance as f32 * HYPERJUMP_SECONDS_PER_PARSEC
+    }
+
+    pub fn remaining(&self) -> f32 {
+        (self.duration - self.elapsed).max(0.)
+    }
+
+    pub fn is_arrived(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+impl Temporal for Hyperjump {
+    fn update(&mut self, delta: f32) -> Result<()> {
+        self.elapsed = (self.elapsed + delta).min(self.duration);
+        Ok(())
+    }
+}
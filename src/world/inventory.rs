@@ -0,0 +1,137 @@
+use super::item::{ItemCatalog, ItemDefinition, ItemId};
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const PLAYER_MAX_CARGO_MASS: f32 = 50_000.; // kg
+pub const PLAYER_MAX_CARGO_VOLUME: f32 = 200.; // meter³
+pub const MAX_TRANSFER_DISTANCE: f32 = 50.; // meter
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub quantity: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Inventory {
+    pub max_mass: f32,   // kg
+    pub max_volume: f32, // meter³
+    stacks: Vec<ItemStack>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum InventoryRef {
+    Player,
+    Structure(Uuid),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ItemTransfer {
+    pub from: InventoryRef,
+    pub to: InventoryRef,
+    pub item: ItemId,
+    pub quantity: u32,
+}
+
+impl Inventory {
+    pub fn new(max_mass: f32, max_volume: f32) -> Self {
+        Self {
+            max_mass,
+            max_volume,
+            stacks: Vec::new(),
+        }
+    }
+
+    pub fn player() -> Self {
+        Self::new(PLAYER_MAX_CARGO_MASS, PLAYER_MAX_CARGO_VOLUME)
+    }
+
+    pub fn stacks(&self) -> &[ItemStack] {
+        &self.stacks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    pub fn count(&self, item: &str) -> u32 {
+        self.stacks
+            .iter()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.quantity)
+            .sum()
+    }
+
+    pub fn mass(&self, catalog: &ItemCatalog) -> f32 {
+        self.total(catalog, |definition| definition.mass)
+    }
+
+    pub fn volume(&self, catalog: &ItemCatalog) -> f32 {
+        self.total(catalog, |definition| definition.volume)
+    }
+
+    fn total(&self, catalog: &ItemCatalog, per_unit: impl Fn(&ItemDefinition) -> f32) -> f32 {
+        self.stacks
+            .iter()
+            .filter_map(|stack| Some(per_unit(catalog.get(&stack.item)?) * stack.quantity as f32))
+            .sum()
+    }
+
+    pub fn check_add(&self, catalog: &ItemCatalog, item: &str, quantity: u32) -> Result<()> {
+        let definition = catalog
+            .get(item)
+            .ok_or_else(|| Error::UnknownItem(item.to_string()))?;
+        let mass = self.mass(catalog) + definition.mass * quantity as f32;
+        let volume = self.volume(catalog) + definition.volume * quantity as f32;
+        if mass > self.max_mass || volume > self.max_volume {
+            return Err(Error::InventoryFull);
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, catalog: &ItemCatalog, item: &str, quantity: u32) -> Result<()> {
+        self.check_add(catalog, item, quantity)?;
+        self.insert(catalog, item, quantity)
+    }
+
+    pub fn insert(&mut self, catalog: &ItemCatalog, item: &str, mut quantity: u32) -> Result<()> {
+        let max_stack = catalog
+            .get(item)
+            .ok_or_else(|| Error::UnknownItem(item.to_string()))?
+            .max_stack;
+        for stack in self.stacks.iter_mut().filter(|stack| stack.item == item) {
+            let added = quantity.min(max_stack.saturating_sub(stack.quantity));
+            stack.quantity += added;
+            quantity -= added;
+        }
+        while quantity > 0 {
+            let added = quantity.min(max_stack);
+            self.stacks.push(ItemStack {
+                item: item.to_string(),
+                quantity: added,
+            });
+            quantity -= added;
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, item: &str, mut quantity: u32) -> Result<()> {
+        if self.count(item) < quantity {
+            return Err(Error::NotEnoughItems);
+        }
+        for stack in self
+            .stacks
+            .iter_mut()
+            .rev()
+            .filter(|stack| stack.item == item)
+        {
+            let removed = quantity.min(stack.quantity);
+            stack.quantity -= removed;
+            quantity -= removed;
+        }
+        self.stacks.retain(|stack| stack.quantity > 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use super::{mining::ResourceType, structure::BlockType};
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+    sync::OnceLock,
+};
+
+pub type ItemId = String;
+
+const BUILTIN_CATALOG: &str = include_str!("../../data/items.json");
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ItemDefinition {
+    pub id: ItemId,
+    pub name: String,
+    pub mass: f32,   // kg per unit
+    pub volume: f32, // meter³ per unit
+    pub max_stack: u32,
+    #[serde(default)]
+    pub block: Option<BlockType>,
+    #[serde(default)]
+    pub resource: Option<ResourceType>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedCatalog {
+    items: Vec<ItemDefinition>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "SavedCatalog", into = "SavedCatalog")]
+pub struct ItemCatalog {
+    items: BTreeMap<ItemId, ItemDefinition>,
+    blocks: BTreeMap<BlockType, ItemId>,
+    resources: BTreeMap<ResourceType, ItemId>,
+}
+
+impl TryFrom<SavedCatalog> for ItemCatalog {
+    type Error = Error;
+
+    fn try_from(saved: SavedCatalog) -> Result<Self> {
+        let mut catalog = ItemCatalog {
+            items: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            resources: BTreeMap::new(),
+        };
+        let mut ids = HashSet::new();
+        for item in saved.items {
+            let valid = ids.insert(item.id.clone())
+                && item.max_stack > 0
+                && item.mass >= 0.
+                && item.volume >= 0.
+                && item
+                    .block
+                    .is_none_or(|block_type| !catalog.blocks.contains_key(&block_type))
+                && item
+                    .resource
+                    .is_none_or(|resource| !catalog.resources.contains_key(&resource));
+            if !valid {
+                return Err(Error::InvalidItemCatalog(item.id));
+            }
+            if let Some(block_type) = item.block {
+                catalog.blocks.insert(block_type, item.id.clone());
+            }
+            if let Some(resource) = item.resource {
+                catalog.resources.insert(resource, item.id.clone());
+            }
+            catalog.items.insert(item.id.clone(), item);
+        }
+        Ok(catalog)
+    }
+}
+
+impl From<ItemCatalog> for SavedCatalog {
+    fn from(catalog: ItemCatalog) -> Self {
+        SavedCatalog {
+            items: catalog.items.into_values().collect(),
+        }
+    }
+}
+
+impl ItemCatalog {
+    pub fn builtin() -> &'static ItemCatalog {
+        static CATALOG: OnceLock<ItemCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| {
+            serde_json::from_str(BUILTIN_CATALOG).expect("the builtin item catalog is valid")
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::ItemCatalogReadError)?;
+        serde_json::from_str(&json).map_err(Error::ItemCatalogDeserializationError)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemDefinition> {
+        self.items.get(id)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &ItemDefinition> {
+        self.items.values()
+    }
+
+    pub fn block_item(&self, block_type: BlockType) -> Result<&ItemId> {
+        self.blocks
+            .get(&block_type)
+            .ok_or_else(|| Error::UnknownItem(format!("{block_type:?}")))
+    }
+
+    pub fn resource_item(&self, resource: ResourceType) -> Result<&ItemId> {
+        self.resources
+            .get(&resource)
+            .ok_or_else(|| Error::UnknownItem(format!("{resource:?}")))
+    }
+}
@@ -0,0 +1,93 @@
+use super::{
+    body::BodyType,
+    environment::{Biome, PlanetEnvironment},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const MAX_MINING_DISTANCE: f32 = 100.; // meter, from the body's surface
+pub const MINING_YIELD: f32 = 10.; // kg per mining action
+pub const MINING_COOLDOWN: f64 = 1.; // second, galaxy time between two mining actions
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Clone, Copy)]
+pub enum ResourceType {
+    Iron,
+    Silicates,
+    Ice,
+    Carbon,
+    Uranium,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Deposit {
+    pub resource: ResourceType,
+    pub amount: f32, // kg
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct MiningRequest {
+    pub body_uuid: Uuid,
+    pub resource: Option<ResourceType>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct MiningResult {
+    pub body_uuid: Uuid,
+    pub resource: ResourceType,
+    pub amount: f32,    // kg
+    pub remaining: f32, // kg, left in the deposit
+}
+
+pub fn generate_deposits(
+    body_type: BodyType,
+    environment: Option<&PlanetEnvironment>,
+    seed: u64,
+) -> Option<Vec<Deposit>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut deposits = Vec::new();
+    let mut add = |resource, amount: f32| {
+        if amount > 0. {
+            deposits.push(Deposit { resource, amount });
+        }
+    };
+    match body_type {
+        BodyType::Asteroid => {
+            add(ResourceType::Iron, rng.gen_range(5.0e4..2.0e5));
+            add(ResourceType::Silicates, rng.gen_range(5.0e4..2.0e5));
+            if rng.gen_bool(0.3) {
+                add(ResourceType::Carbon, rng.gen_range(1.0e4..5.0e4));
+            }
+            if rng.gen_bool(0.05) {
+                add(ResourceType::Uranium, rng.gen_range(1.0e3..5.0e3));
+            }
+        }
+        BodyType::Comet => {
+            add(ResourceType::Ice, rng.gen_range(1.0e5..1.0e6));
+            add(ResourceType::Carbon, rng.gen_range(1.0e4..1.0e5));
+        }
+        BodyType::Planet | BodyType::Moon => {
+            let environment = environment?;
+            let scale = match body_type {
+                BodyType::Planet => 1.0e7,
+                _ => 1.0e6,
+            } * environment.resource_richness as f32;
+            add(ResourceType::Iron, scale * rng.gen_range(0.5..1.));
+            add(ResourceType::Silicates, scale * rng.gen_range(0.5..1.));
+            if matches!(environment.biome, Biome::Ice | Biome::Ocean) {
+                add(ResourceType::Ice, scale * rng.gen_range(0.5..1.));
+            }
+            if matches!(
+                environment.biome,
+                Biome::Ocean | Biome::Temperate | Biome::Jungle
+            ) {
+                add(ResourceType::Carbon, scale * rng.gen_range(0.1..0.5));
+            }
+            if environment.biome == Biome::Volcanic || environment.resource_richness > 0.8 {
+                add(ResourceType::Uranium, scale * rng.gen_range(0.01..0.05));
+            }
+        }
+        _ => return None,
+    }
+    Some(deposits)
+}
@@ -1,5 +1,25 @@
+pub mod blueprint;
 pub mod body;
+pub mod clock;
+pub mod collision;
+pub mod docking;
+pub mod entity;
+pub mod environment;
+pub mod event;
+pub mod flight;
 pub mod galaxy;
+pub mod hazard;
+pub mod hyperjump;
+pub mod inventory;
+pub mod item;
+pub mod mining;
+pub mod orbit;
 pub mod player;
+pub mod production;
+pub mod route;
+pub mod stellar;
+pub mod structure;
 pub mod system;
+pub mod system_cache;
+pub mod system_index;
 pub mod temporal;
@@ -0,0 +1,41 @@
+use super::{entity::Entities, hazard::GRAVITATIONAL_CONSTANT};
+use nalgebra::{Unit, UnitQuaternion, Vector3};
+use std::{collections::BTreeSet, f64::consts::TAU};
+use uuid::Uuid;
+
+pub fn orbital_rate(entities: &Entities, uuid: &Uuid) -> Option<f32> {
+    entities.body_types.get(uuid)?;
+    let parent_mass = *entities.masses.get(entities.parents.get(uuid)?)? as f64;
+    let radius = entities.positions.get(uuid)?.norm() as f64;
+    if radius <= 0. {
+        return None;
+    }
+    Some((GRAVITATIONAL_CONSTANT * parent_mass / radius.powi(3)).sqrt() as f32)
+}
+
+pub fn orbiting_bodies(entities: &Entities) -> BTreeSet<Uuid> {
+    entities
+        .parents
+        .keys()
+        .filter(|uuid| orbital_rate(entities, uuid).is_some())
+        .copied()
+        .collect()
+}
+
+pub fn advance_orbits(entities: &mut Entities, delta: f32) {
+    for uuid in orbiting_bodies(entities) {
+        let Some(rate) = orbital_rate(entities, &uuid) else {
+            continue;
+        };
+        let position = entities.positions[&uuid];
+        let velocity = entities.velocities.get(&uuid).copied().unwrap_or_default();
+        let axis =
+            Unit::try_new(position.cross(&velocity), f32::EPSILON).unwrap_or(Vector3::z_axis());
+        let angle = (rate as f64 * delta as f64 % TAU) as f32;
+        let position = UnitQuaternion::from_axis_angle(&axis, angle) * position;
+        entities.positions.insert(uuid, position);
+        entities
+            .velocities
+            .insert(uuid, axis.cross(&position) * rate);
+    }
+}
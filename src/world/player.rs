@@ -1,23 +1,103 @@
-use crate::SystemCoordsRepr;
+use super::{
+    hazard::HazardReport, hyperjump::Hyperjump, inventory::Inventory, item::ItemCatalog,
+    structure::BlockType, temporal::Temporal,
+};
+use crate::{Result, SystemCoordsRepr};
 use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+pub const PLAYER_SPAWN_COORDS: Vector3<SystemCoordsRepr> = Vector3::new(100., 100., 100.);
+pub const MAX_PLAYER_HEALTH: f32 = 100.;
+pub const PLAYER_MASS: f32 = 100.; // kg
+
+pub const STARTER_BLOCKS: [(BlockType, u32); 5] = [
+    (BlockType::Hull, 50),
+    (BlockType::Thruster, 4),
+    (BlockType::Reactor, 2),
+    (BlockType::Cargo, 4),
+    (BlockType::Cockpit, 1),
+];
+
+fn max_player_health() -> f32 {
+    MAX_PLAYER_HEALTH
+}
+
+fn starter_inventory(catalog: &ItemCatalog) -> Inventory {
+    let mut inventory = Inventory::player();
+    for (block_type, count) in STARTER_BLOCKS {
+        if let Ok(item) = catalog.block_item(block_type) {
+            let _ = inventory.insert(catalog, item, count);
+        }
+    }
+    inventory
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Player {
     pub coords: Vector3<SystemCoordsRepr>,
     pub nickname: String,
     pub own_system_uuid: Uuid,
     pub current_system_uuid: Uuid,
+    #[serde(default)]
+    pub hyperjump: Option<Hyperjump>,
+    #[serde(default = "max_player_health")]
+    pub health: f32,
+    #[serde(default = "Inventory::player")]
+    pub inventory: Inventory,
+    #[serde(default)]
+    pub docked_station_uuid: Option<Uuid>,
+    #[serde(skip)]
+    pub hazard: HazardReport,
 }
 
 impl Player {
-    pub fn new(coords: Vector3<SystemCoordsRepr>, nickname: String, system_uuid: Uuid) -> Self {
+    pub fn new(
+        coords: Vector3<SystemCoordsRepr>,
+        nickname: String,
+        system_uuid: Uuid,
+        catalog: &ItemCatalog,
+    ) -> Self {
         Self {
             coords,
             nickname,
             own_system_uuid: system_uuid,
             current_system_uuid: system_uuid,
+            hyperjump: None,
+            health: MAX_PLAYER_HEALTH,
+            inventory: starter_inventory(catalog),
+            docked_station_uuid: None,
+            hazard: HazardReport::default(),
+        }
+    }
+
+    pub fn respawn(&mut self, coords: Vector3<SystemCoordsRepr>) {
+        self.coords = coords;
+        self.current_system_uuid = self.own_system_uuid;
+        self.hyperjump = None;
+        self.docked_station_uuid = None;
+        self.health = MAX_PLAYER_HEALTH;
+        self.hazard = HazardReport::default();
+    }
+
+    pub fn is_in_transit(&self) -> bool {
+        self.hyperjump.is_some()
+    }
+
+    pub fn is_docked(&self) -> bool {
+        self.docked_station_uuid.is_some()
+    }
+}
+
+impl Temporal for Player {
+    fn update(&mut self, delta: f32) -> Result<()> {
+        if let Some(hyperjump) = &mut self.hyperjump {
+            hyperjump.update(delta)?;
+            if hyperjump.is_arrived() {
+                self.current_system_uuid = hyperjump.target_system_uuid;
+                self.hyperjump = None;
+            }
         }
+        Ok(())
     }
 }
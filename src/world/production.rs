@@ -0,0 +1,193 @@
+use super::{body::BodyType, entity::Entities, inventory::ItemStack, item::ItemCatalog};
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path, sync::OnceLock};
+use uuid::Uuid;
+
+pub const MAX_PRODUCTION_QUEUE: usize = 20; // jobs of each owner per facility entity
+pub const STATION_PRODUCTION_SLOTS: u32 = 4; // jobs of each facility run at once
+
+pub type RecipeId = String;
+
+const BUILTIN_RECIPES: &str = include_str!("../../data/recipes.json");
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Clone, Copy)]
+pub enum Facility {
+    Refinery,
+    Assembler,
+}
+
+impl Facility {
+    pub const ALL: [Facility; 2] = [Facility::Refinery, Facility::Assembler];
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Recipe {
+    pub id: RecipeId,
+    pub name: String,
+    pub facility: Facility,
+    pub inputs: Vec<ItemStack>,
+    pub outputs: Vec<ItemStack>,
+    pub duration: f32, // second
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedRecipes {
+    recipes: Vec<Recipe>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "SavedRecipes", into = "SavedRecipes")]
+pub struct RecipeCatalog {
+    recipes: BTreeMap<RecipeId, Recipe>,
+}
+
+impl TryFrom<SavedRecipes> for RecipeCatalog {
+    type Error = Error;
+
+    fn try_from(saved: SavedRecipes) -> Result<Self> {
+        let mut recipes = BTreeMap::new();
+        for recipe in saved.recipes {
+            let valid = !recipes.contains_key(&recipe.id)
+                && recipe.duration > 0.
+                && !recipe.outputs.is_empty()
+                && recipe
+                    .inputs
+                    .iter()
+                    .chain(recipe.outputs.iter())
+                    .all(|stack| stack.quantity > 0);
+            if !valid {
+                return Err(Error::InvalidRecipeCatalog(recipe.id));
+            }
+            recipes.insert(recipe.id.clone(), recipe);
+        }
+        Ok(RecipeCatalog { recipes })
+    }
+}
+
+impl From<RecipeCatalog> for SavedRecipes {
+    fn from(catalog: RecipeCatalog) -> Self {
+        SavedRecipes {
+            recipes: catalog.recipes.into_values().collect(),
+        }
+    }
+}
+
+impl RecipeCatalog {
+    pub fn builtin() -> &'static RecipeCatalog {
+        static CATALOG: OnceLock<RecipeCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| {
+            serde_json::from_str(BUILTIN_RECIPES).expect("the builtin recipe catalog is valid")
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::RecipeCatalogReadError)?;
+        serde_json::from_str(&json).map_err(Error::RecipeCatalogDeserializationError)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Recipe> {
+        self.recipes.get(id)
+    }
+
+    pub fn recipes(&self) -> impl Iterator<Item = &Recipe> {
+        self.recipes.values()
+    }
+
+    pub fn check_items(&self, items: &ItemCatalog) -> Result<()> {
+        for recipe in self.recipes.values() {
+            if recipe
+                .inputs
+                .iter()
+                .chain(recipe.outputs.iter())
+                .any(|stack| items.get(&stack.item).is_none())
+            {
+                return Err(Error::InvalidRecipeCatalog(recipe.id.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ProductionJob {
+    pub owner_uuid: Uuid,
+    pub recipe: RecipeId,
+    pub facility: Facility,
+    pub outputs: Vec<ItemStack>,
+    pub duration: f32, // second
+    pub elapsed: f32,  // second
+}
+
+impl ProductionJob {
+    pub fn new(owner_uuid: Uuid, recipe: &Recipe) -> Self {
+        Self {
+            owner_uuid,
+            recipe: recipe.id.clone(),
+            facility: recipe.facility,
+            outputs: recipe.outputs.clone(),
+            duration: recipe.duration,
+            elapsed: 0.,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub fn remaining(&self) -> f32 {
+        (self.duration - self.elapsed).max(0.)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ProductionOrder {
+    pub facility_uuid: Uuid,
+    pub recipe: RecipeId,
+    pub runs: u32,
+}
+
+pub fn production_slots(entities: &Entities, uuid: &Uuid, facility: Facility) -> u32 {
+    if entities.body_types.get(uuid) == Some(&BodyType::Station) {
+        return STATION_PRODUCTION_SLOTS;
+    }
+    entities
+        .structures
+        .get(uuid)
+        .map_or(0, |structure| structure.production_slots(facility))
+}
+
+pub fn advance_production(entities: &mut Entities, delta: f32) -> Vec<(Uuid, ProductionJob)> {
+    let mut finished = Vec::new();
+    let uuids: Vec<Uuid> = entities.productions.keys().copied().collect();
+    for uuid in uuids {
+        for facility in Facility::ALL {
+            let slots = production_slots(entities, &uuid, facility);
+            let Some(jobs) = entities.productions.get_mut(&uuid) else {
+                continue;
+            };
+            let mut budgets = vec![delta; slots as usize]; // second, left to each slot
+            for job in jobs
+                .iter_mut()
+                .filter(|job| job.facility == facility && !job.is_finished())
+            {
+                let Some(budget) = budgets.iter_mut().max_by(|a, b| a.total_cmp(b)) else {
+                    break;
+                };
+                if *budget <= 0. {
+                    break;
+                }
+                if *budget >= job.remaining() {
+                    *budget -= job.remaining();
+                    job.elapsed = job.duration;
+                    finished.push((uuid, job.clone()));
+                } else {
+                    job.elapsed += *budget;
+                    *budget = 0.;
+                }
+            }
+        }
+    }
+    finished
+}
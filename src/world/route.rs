@@ -0,0 +1,131 @@
+use super::{hyperjump::Hyperjump, system_index::SystemIndex};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct JumpRoute {
+    pub systems: Vec<Uuid>,
+    pub distance: f64,       // parsec
+    pub total_duration: f32, // second
+}
+
+impl JumpRoute {
+    pub fn jump_count(&self) -> usize {
+        self.systems.len().saturating_sub(1)
+    }
+}
+
+struct OpenNode {
+    uuid: Uuid,
+    estimated_cost: f32,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    // Reversed so that the binary heap pops the cheapest node first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimated_cost
+            .total_cmp(&self.estimated_cost)
+            .then_with(|| other.uuid.cmp(&self.uuid))
+    }
+}
+
+pub fn find_route(
+    index: &SystemIndex,
+    from: Uuid,
+    to: Uuid,
+    max_jump_range: f64,
+) -> Option<JumpRoute> {
+    let goal_position = index.get(&to)?.galaxy_position();
+    let heuristic = |uuid: &Uuid, distance_to_goal: f64| {
+        if *uuid == to {
+            0.
+        } else {
+            Hyperjump::duration_for_distance(distance_to_goal)
+        }
+    };
+
+    let start_position = index.get(&from)?.galaxy_position();
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Uuid, (Uuid, f64)> = HashMap::new();
+    let mut best_cost: HashMap<Uuid, f32> = HashMap::from([(from, 0.)]);
+    open.push(OpenNode {
+        uuid: from,
+        estimated_cost: heuristic(&from, (goal_position - start_position).norm()),
+    });
+
+    while let Some(OpenNode {
+        uuid,
+        estimated_cost,
+    }) = open.pop()
+    {
+        let cost = best_cost[&uuid];
+        if uuid == to {
+            return Some(rebuild_route(&came_from, to, cost));
+        }
+        let position = index.get(&uuid)?.galaxy_position();
+        if estimated_cost > cost + heuristic(&uuid, (goal_position - position).norm()) {
+            continue;
+        }
+
+        for neighbour in index.within_radius(&position, max_jump_range) {
+            if neighbour.uuid == uuid {
+                continue;
+            }
+            let neighbour_position = neighbour.galaxy_position();
+            let distance = (neighbour_position - position).norm();
+            let neighbour_cost = cost + Hyperjump::duration_for_distance(distance);
+            if best_cost
+                .get(&neighbour.uuid)
+                .is_some_and(|known_cost| *known_cost <= neighbour_cost)
+            {
+                continue;
+            }
+            best_cost.insert(neighbour.uuid, neighbour_cost);
+            came_from.insert(neighbour.uuid, (uuid, distance));
+            open.push(OpenNode {
+                uuid: neighbour.uuid,
+                estimated_cost: neighbour_cost
+                    + heuristic(&neighbour.uuid, (goal_position - neighbour_position).norm()),
+            });
+        }
+    }
+
+    None
+}
+
+fn rebuild_route(came_from: &HashMap<Uuid, (Uuid, f64)>, to: Uuid, cost: f32) -> JumpRoute {
+    let mut systems = vec![to];
+    let mut distance = 0.;
+    let mut current = to;
+    while let Some((previous, jump_distance)) = came_from.get(&current) {
+        systems.push(*previous);
+        distance += jump_distance;
+        current = *previous;
+    }
+    systems.reverse();
+
+    JumpRoute {
+        systems,
+        distance,
+        total_duration: cost,
+    }
+}
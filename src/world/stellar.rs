@@ -0,0 +1,96 @@
+use super::system::CenterType;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+pub const SUN_TEMPERATURE: f64 = 5778.; // kelvin
+pub const SUN_RADIUS: f64 = 695_700_000.; // meter
+pub const SCHWARZSCHILD_RADIUS_PER_SOLAR_MASS: f64 = 2953.; // meter
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub struct HabitableZone {
+    pub inner: f64, // au
+    pub outer: f64, // au
+}
+
+impl HabitableZone {
+    pub fn from_luminosity(luminosity: f64) -> Option<Self> {
+        if luminosity <= 0. {
+            return None;
+        }
+        Some(Self {
+            inner: (luminosity / 1.1).sqrt(),
+            outer: (luminosity / 0.53).sqrt(),
+        })
+    }
+
+    pub fn contains(&self, distance: f64) -> bool {
+        (self.inner..=self.outer).contains(&distance)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct StellarProperties {
+    pub mass: f64,        // solar mass
+    pub luminosity: f64,  // solar luminosity
+    pub temperature: f64, // kelvin
+    pub radius: f64,      // solar radius
+    pub habitable_zone: Option<HabitableZone>,
+}
+
+impl StellarProperties {
+    fn main_sequence(mass: f64) -> Self {
+        let luminosity = mass.powf(3.5);
+        let radius = mass.powf(0.8);
+        Self {
+            mass,
+            luminosity,
+            temperature: SUN_TEMPERATURE * (luminosity / (radius * radius)).powf(0.25),
+            radius,
+            habitable_zone: HabitableZone::from_luminosity(luminosity),
+        }
+    }
+
+    fn black_hole(mass: f64) -> Self {
+        Self {
+            mass,
+            luminosity: 0.,
+            temperature: 0.,
+            radius: mass * SCHWARZSCHILD_RADIUS_PER_SOLAR_MASS / SUN_RADIUS,
+            habitable_zone: None,
+        }
+    }
+
+    fn neutron_star(mass: f64, radius: f64, temperature: f64) -> Self {
+        let radius = radius / SUN_RADIUS;
+        let luminosity = radius * radius * (temperature / SUN_TEMPERATURE).powi(4);
+        Self {
+            mass,
+            luminosity,
+            temperature,
+            radius,
+            habitable_zone: None,
+        }
+    }
+
+    pub fn generate(center_type: CenterType, seed: u64) -> Vec<StellarProperties> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        // Skewed towards small stars, like the real initial mass function.
+        let main_sequence_mass = |rng: &mut StdRng| 0.1 * 200_f64.powf(rng.gen::<f64>().powi(3));
+
+        match center_type {
+            CenterType::OneStar => vec![Self::main_sequence(main_sequence_mass(&mut rng))],
+            CenterType::TwoStars => (0..2)
+                .map(|_| Self::main_sequence(main_sequence_mass(&mut rng)))
+                .collect(),
+            CenterType::ThreeStars => (0..3)
+                .map(|_| Self::main_sequence(main_sequence_mass(&mut rng)))
+                .collect(),
+            CenterType::BlackHole => vec![Self::black_hole(rng.gen_range(5. ..30.))],
+            CenterType::NeutronStar => vec![Self::neutron_star(
+                rng.gen_range(1.1..2.3),
+                rng.gen_range(10_000. ..14_000.),
+                rng.gen_range(100_000. ..1_000_000.),
+            )],
+        }
+    }
+}
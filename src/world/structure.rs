@@ -0,0 +1,443 @@
+use super::production::Facility;
+use crate::error::Error;
+use crate::Result;
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+pub type BlockCoordsRepr = i32;
+
+pub const BLOCK_SIZE: f32 = 1.; // meter
+pub const BLOCK_HALF_DIAGONAL: f32 = BLOCK_SIZE * 0.866_025_4; // meter, √3/2
+pub const CHUNK_SIZE: BlockCoordsRepr = 16; // block
+const BYTES_PER_ENCODED_BLOCK: usize = 3;
+pub const MAX_BUILD_RANGE: f32 = 50.; // meter
+
+const NEIGHBOR_OFFSETS: [Vector3<BlockCoordsRepr>; 6] = [
+    Vector3::new(1, 0, 0),
+    Vector3::new(-1, 0, 0),
+    Vector3::new(0, 1, 0),
+    Vector3::new(0, -1, 0),
+    Vector3::new(0, 0, 1),
+    Vector3::new(0, 0, -1),
+];
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Clone, Copy)]
+pub enum BlockType {
+    Hull,
+    Armor,
+    Thruster,
+    Reactor,
+    Cargo,
+    Cockpit,
+    Refinery,
+    Assembler,
+}
+
+impl BlockType {
+    pub fn mass(&self) -> f32 {
+        match self {
+            BlockType::Hull => 100.,
+            BlockType::Armor => 400.,
+            BlockType::Thruster => 200.,
+            BlockType::Reactor => 500.,
+            BlockType::Cargo => 150.,
+            BlockType::Cockpit => 150.,
+            BlockType::Refinery => 1_000.,
+            BlockType::Assembler => 800.,
+        } // kg
+    }
+
+    pub fn thrust(&self) -> f32 {
+        match self {
+            BlockType::Thruster => 50_000.,
+            _ => 0.,
+        } // newton
+    }
+
+    pub fn cargo_capacity(&self) -> (f32, f32) {
+        match self {
+            BlockType::Cargo => (5_000., 1.),
+            _ => (0., 0.),
+        } // kg, meter³
+    }
+
+    pub fn power(&self) -> f32 {
+        match self {
+            BlockType::Reactor => 10_000.,
+            BlockType::Thruster => -1_000.,
+            BlockType::Cockpit => -200.,
+            BlockType::Refinery => -5_000.,
+            BlockType::Assembler => -3_000.,
+            _ => 0.,
+        } // watt
+    }
+
+    pub fn facility(&self) -> Option<Facility> {
+        match self {
+            BlockType::Refinery => Some(Facility::Refinery),
+            BlockType::Assembler => Some(Facility::Assembler),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            BlockType::Hull => 0,
+            BlockType::Armor => 1,
+            BlockType::Thruster => 2,
+            BlockType::Reactor => 3,
+            BlockType::Cargo => 4,
+            BlockType::Cockpit => 5,
+            BlockType::Refinery => 6,
+            BlockType::Assembler => 7,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<BlockType> {
+        match id {
+            0 => Some(BlockType::Hull),
+            1 => Some(BlockType::Armor),
+            2 => Some(BlockType::Thruster),
+            3 => Some(BlockType::Reactor),
+            4 => Some(BlockType::Cargo),
+            5 => Some(BlockType::Cockpit),
+            6 => Some(BlockType::Refinery),
+            7 => Some(BlockType::Assembler),
+            _ => None,
+        }
+    }
+}
+
+fn block_distance(position: &Vector3<BlockCoordsRepr>) -> f32 {
+    position.map(|coord| coord as f32).norm() * BLOCK_SIZE
+}
+
+pub fn chunk_coords(position: &Vector3<BlockCoordsRepr>) -> Vector3<BlockCoordsRepr> {
+    position.map(|coord| coord.div_euclid(CHUNK_SIZE))
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum BlockEdit {
+    Place(BlockType),
+    Remove,
+    Replace(BlockType),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct StructureChange {
+    pub system_uuid: Uuid,
+    pub structure_uuid: Uuid,
+    pub position: Vector3<BlockCoordsRepr>,
+    pub block_type: Option<BlockType>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedStructure {
+    blocks: Vec<(Vector3<BlockCoordsRepr>, BlockType)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(from = "SavedStructure", into = "SavedStructure")]
+pub struct Structure {
+    blocks: HashMap<Vector3<BlockCoordsRepr>, BlockType>,
+    mass: f32,                        // kg
+    weighted_positions: Vector3<f64>, // kg·meter, relative to the grid origin
+    second_moment: Matrix3<f64>,      // kg·meter², relative to the grid origin
+    farthest_block: f32,              // meter, from the grid origin
+    thrust: f32,                      // newton
+    power_generation: f32,            // watt
+    power_consumption: f32,           // watt
+    max_cargo_mass: f32,              // kg
+    max_cargo_volume: f32,            // meter³
+    production_blocks: BTreeMap<Facility, u32>,
+    chunks: HashMap<Vector3<BlockCoordsRepr>, usize>, // block count per chunk
+    dirty_chunks: HashSet<Vector3<BlockCoordsRepr>>,
+}
+
+impl From<SavedStructure> for Structure {
+    fn from(saved: SavedStructure) -> Self {
+        let mut structure = Structure::default();
+        for (position, block_type) in saved.blocks {
+            structure.set_block(position, Some(block_type));
+        }
+        structure
+    }
+}
+
+impl From<Structure> for SavedStructure {
+    fn from(structure: Structure) -> Self {
+        SavedStructure {
+            blocks: structure.blocks.into_iter().collect(),
+        }
+    }
+}
+
+impl Structure {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn starter_ship() -> Self {
+        let mut structure = Self::new();
+        let blocks = [
+            ((0, 0, 1), BlockType::Cockpit),
+            ((0, 0, 0), BlockType::Reactor),
+            ((1, 0, 0), BlockType::Hull),
+            ((-1, 0, 0), BlockType::Hull),
+            ((0, 0, -1), BlockType::Thruster),
+        ];
+        for ((x, y, z), block_type) in blocks {
+            structure.set_block(Vector3::new(x, y, z), Some(block_type));
+        }
+        structure
+    }
+
+    pub fn block(&self, position: &Vector3<BlockCoordsRepr>) -> Option<BlockType> {
+        self.blocks.get(position).copied()
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = (&Vector3<BlockCoordsRepr>, &BlockType)> {
+        self.blocks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_adjacent(&self, position: &Vector3<BlockCoordsRepr>) -> bool {
+        self.blocks.is_empty()
+            || NEIGHBOR_OFFSETS
+                .iter()
+                .any(|offset| self.blocks.contains_key(&(position + offset)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn stays_connected_without(&self, position: &Vector3<BlockCoordsRepr>) -> bool {
+        let Some(start) = NEIGHBOR_OFFSETS
+            .iter()
+            .map(|offset| position + offset)
+            .find(|neighbor| self.blocks.contains_key(neighbor))
+        else {
+            // Only a lone block has no neighbor.
+            return self.blocks.len() <= 1;
+        };
+
+        let mut visited = HashSet::from([*position, start]);
+        let mut pending = vec![start];
+        while let Some(current) = pending.pop() {
+            for offset in NEIGHBOR_OFFSETS.iter() {
+                let neighbor = current + offset;
+                if self.blocks.contains_key(&neighbor) && visited.insert(neighbor) {
+                    pending.push(neighbor);
+                }
+            }
+        }
+        // The removed position is counted as visited.
+        visited.len() == self.blocks.len()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        let Some(start) = self.blocks.keys().next() else {
+            return true;
+        };
+        let mut visited = HashSet::from([*start]);
+        let mut pending = vec![*start];
+        while let Some(current) = pending.pop() {
+            for offset in NEIGHBOR_OFFSETS.iter() {
+                let neighbor = current + offset;
+                if self.blocks.contains_key(&neighbor) && visited.insert(neighbor) {
+                    pending.push(neighbor);
+                }
+            }
+        }
+        visited.len() == self.blocks.len()
+    }
+
+    pub fn set_block(
+        &mut self,
+        position: Vector3<BlockCoordsRepr>,
+        block_type: Option<BlockType>,
+    ) -> Option<BlockType> {
+        let previous = match block_type {
+            Some(block_type) => self.blocks.insert(position, block_type),
+            None => self.blocks.remove(&position),
+        };
+        if let Some(previous) = previous {
+            self.account(&position, previous, -1.);
+        }
+        if let Some(block_type) = block_type {
+            self.account(&position, block_type, 1.);
+        }
+        let distance = block_distance(&position);
+        if block_type.is_some() {
+            self.farthest_block = self.farthest_block.max(distance);
+        } else if previous.is_some() && distance >= self.farthest_block {
+            self.farthest_block = self.blocks.keys().map(block_distance).fold(0., f32::max);
+        }
+
+        let chunk = chunk_coords(&position);
+        let count = self.chunks.entry(chunk).or_default();
+        match (previous, block_type) {
+            (None, Some(_)) => *count += 1,
+            (Some(_), None) => *count -= 1,
+            _ => {}
+        }
+        if *count == 0 {
+            self.chunks.remove(&chunk);
+        }
+        self.dirty_chunks.insert(chunk);
+        previous
+    }
+
+    pub fn chunk_coords(&self) -> Vec<Vector3<BlockCoordsRepr>> {
+        let mut chunks: Vec<_> = self.chunks.keys().copied().collect();
+        chunks.sort_by_key(|chunk| (chunk.x, chunk.y, chunk.z));
+        chunks
+    }
+
+    pub fn dirty_chunks(&self) -> Vec<Vector3<BlockCoordsRepr>> {
+        let mut chunks: Vec<_> = self.dirty_chunks.iter().copied().collect();
+        chunks.sort_by_key(|chunk| (chunk.x, chunk.y, chunk.z));
+        chunks
+    }
+
+    pub fn take_dirty_chunks(&mut self) -> Vec<Vector3<BlockCoordsRepr>> {
+        let mut chunks: Vec<_> = self.dirty_chunks.drain().collect();
+        chunks.sort_by_key(|chunk| (chunk.x, chunk.y, chunk.z));
+        chunks
+    }
+
+    pub fn encode_chunk(&self, chunk: &Vector3<BlockCoordsRepr>) -> Vec<u8> {
+        let origin = chunk * CHUNK_SIZE;
+        let mut bytes = Vec::new();
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let Some(block_type) = self.blocks.get(&(origin + Vector3::new(x, y, z)))
+                    else {
+                        continue;
+                    };
+                    let index = (x + CHUNK_SIZE * (y + CHUNK_SIZE * z)) as u16;
+                    bytes.extend(index.to_le_bytes());
+                    bytes.push(block_type.id());
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn decode_chunk(&mut self, chunk: &Vector3<BlockCoordsRepr>, bytes: &[u8]) -> Result<()> {
+        if !bytes.len().is_multiple_of(BYTES_PER_ENCODED_BLOCK) {
+            return Err(Error::ChunkDecodingError);
+        }
+        let origin = chunk * CHUNK_SIZE;
+        for encoded in bytes.chunks_exact(BYTES_PER_ENCODED_BLOCK) {
+            let index = u16::from_le_bytes([encoded[0], encoded[1]]) as BlockCoordsRepr;
+            if index >= CHUNK_SIZE.pow(3) {
+                return Err(Error::ChunkDecodingError);
+            }
+            let block_type = BlockType::from_id(encoded[2]).ok_or(Error::ChunkDecodingError)?;
+            let position = Vector3::new(
+                index % CHUNK_SIZE,
+                index / CHUNK_SIZE % CHUNK_SIZE,
+                index / (CHUNK_SIZE * CHUNK_SIZE),
+            );
+            self.set_block(origin + position, Some(block_type));
+        }
+        Ok(())
+    }
+
+    fn account(&mut self, position: &Vector3<BlockCoordsRepr>, block_type: BlockType, sign: f32) {
+        let mass = block_type.mass() * sign;
+        self.mass += mass;
+        let position = position.map(|coord| coord as f64 * BLOCK_SIZE as f64);
+        self.weighted_positions += position * mass as f64;
+        self.second_moment += position * position.transpose() * mass as f64;
+        self.thrust += block_type.thrust() * sign;
+        let (cargo_mass, cargo_volume) = block_type.cargo_capacity();
+        self.max_cargo_mass += cargo_mass * sign;
+        self.max_cargo_volume += cargo_volume * sign;
+        if let Some(facility) = block_type.facility() {
+            let count = self.production_blocks.entry(facility).or_default();
+            if sign > 0. {
+                *count += 1;
+            } else {
+                *count -= 1;
+            }
+        }
+        let power = block_type.power() * sign;
+        if block_type.power() > 0. {
+            self.power_generation += power;
+        } else {
+            self.power_consumption -= power;
+        }
+    }
+
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    pub fn center_of_mass(&self) -> Vector3<f32> {
+        if self.mass <= 0. {
+            return Vector3::default();
+        }
+        (self.weighted_positions / self.mass as f64).map(|coord| coord as f32)
+    }
+
+    pub fn inertia_tensor(&self) -> Matrix3<f32> {
+        if self.mass <= 0. {
+            return Matrix3::zeros();
+        }
+        let mass = self.mass as f64;
+        let center = self.weighted_positions / mass;
+        let around_origin = Matrix3::identity() * self.second_moment.trace() - self.second_moment;
+        let around_center = around_origin
+            - (Matrix3::identity() * center.norm_squared() - center * center.transpose()) * mass;
+        let blocks = Matrix3::identity() * mass * (BLOCK_SIZE as f64).powi(2) / 6.;
+        (around_center + blocks).map(|value| value as f32)
+    }
+
+    pub fn bounding_radius(&self) -> f32 {
+        if self.blocks.is_empty() {
+            return 0.;
+        }
+        self.farthest_block + BLOCK_HALF_DIAGONAL
+    }
+
+    pub fn thrust(&self) -> f32 {
+        self.thrust
+    }
+
+    pub fn max_cargo_mass(&self) -> f32 {
+        self.max_cargo_mass
+    }
+
+    pub fn max_cargo_volume(&self) -> f32 {
+        self.max_cargo_volume
+    }
+
+    pub fn production_slots(&self, facility: Facility) -> u32 {
+        self.production_blocks.get(&facility).copied().unwrap_or(0)
+    }
+
+    pub fn power_generation(&self) -> f32 {
+        self.power_generation
+    }
+
+    pub fn power_consumption(&self) -> f32 {
+        self.power_consumption
+    }
+
+    pub fn effective_thrust(&self) -> f32 {
+        if self.power_consumption <= self.power_generation {
+            self.thrust
+        } else {
+            self.thrust * self.power_generation / self.power_consumption
+        }
+    }
+}
@@ -1,11 +1,41 @@
-use super::{body::Body, temporal::Temporal};
+use super::{
+    body::Body,
+    collision::{detect_collisions, resolve_collisions, Contact, SpatialHash},
+    entity::Entities,
+    environment::PlanetEnvironment,
+    flight::apply_flight,
+    inventory::Inventory,
+    item::ItemId,
+    mining::{generate_deposits, ResourceType},
+    orbit::advance_orbits,
+    player::{Player, PLAYER_MASS},
+    production::{advance_production, ProductionJob},
+    stellar::{HabitableZone, StellarProperties},
+    structure::{BlockCoordsRepr, BlockType, Structure},
+    temporal::Temporal,
+};
+use crate::error::Error;
 use crate::Result;
 use crate::{GalaxyCoordsRepr, GalaxyOffsetRepr};
-use nalgebra::Vector3;
+use nalgebra::{UnitQuaternion, Vector3};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub const AU_PER_PARSEC: f64 = 206_264.806;
+pub const CATCH_UP_STEP: f64 = 1.; // second, longest step when catching up
+pub const MAX_CATCH_UP_STEPS: u32 = 3_600; // flight isn't caught up further back
+
+pub fn galaxy_position(
+    coords: &Vector3<GalaxyCoordsRepr>,
+    offset: &Vector3<GalaxyOffsetRepr>,
+) -> Vector3<f64> {
+    coords.map(|coord| coord as f64) + offset.map(|offset| offset as f64 / AU_PER_PARSEC)
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Default)]
 pub enum CenterType {
+    #[default]
     OneStar,
     TwoStars,
     ThreeStars,
@@ -13,12 +43,6 @@ pub enum CenterType {
     NeutronStar,
 }
 
-impl Default for CenterType {
-    fn default() -> Self {
-        CenterType::OneStar
-    }
-}
-
 impl From<u32> for CenterType {
     fn from(value: u32) -> Self {
         match value {
@@ -37,7 +61,30 @@ pub struct System {
     pub coords: Vector3<GalaxyCoordsRepr>, // parsec
     pub offset: Vector3<GalaxyOffsetRepr>, // au
     pub center_type: CenterType,
-    pub bodies: Vec<Body>,
+    #[serde(default)]
+    pub stellar_properties: Vec<StellarProperties>,
+    #[serde(default)]
+    pub entities: Entities,
+    #[serde(default, rename = "bodies", skip_serializing)]
+    pub legacy_bodies: Vec<Body>,
+    #[serde(default)]
+    pub simulated_time: f64, // second, galaxy time the system was simulated up to
+    #[serde(skip)]
+    pub(crate) spatial_hash: SpatialHash,
+    #[serde(skip)]
+    pub collisions: Vec<Contact>,
+    #[serde(skip)]
+    pub finished_jobs: Vec<(Uuid, ProductionJob)>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SystemView {
+    pub coords: Vector3<GalaxyCoordsRepr>, // parsec
+    pub offset: Vector3<GalaxyOffsetRepr>, // au
+    pub center_type: CenterType,
+    pub stellar_properties: Vec<StellarProperties>,
+    pub entities: Entities,  // without structure blocks
+    pub simulated_time: f64, // second
 }
 
 impl System {
@@ -46,20 +93,593 @@ impl System {
         offset: Vector3<GalaxyOffsetRepr>,
         center_type: CenterType,
     ) -> Self {
-        Self {
+        let mut system = Self {
             coords,
             offset,
             center_type,
-            bodies: Vec::new(),
+            stellar_properties: Vec::new(),
+            entities: Entities::default(),
+            legacy_bodies: Vec::new(),
+            simulated_time: 0.,
+            spatial_hash: SpatialHash::default(),
+            collisions: Vec::new(),
+            finished_jobs: Vec::new(),
+        };
+        system.stellar_properties = StellarProperties::generate(center_type, system.seed());
+        system
+    }
+
+    pub fn seed(&self) -> u64 {
+        let mut seed: u64 = 0xcbf2_9ce4_8422_2325;
+        let values = self
+            .coords
+            .iter()
+            .map(|coord| *coord as i64)
+            .chain(self.offset.iter().map(|offset| *offset as i64));
+        for value in values {
+            seed ^= value as u64;
+            seed = seed.wrapping_mul(0x0100_0000_01b3);
         }
+        seed
+    }
+
+    pub fn total_mass(&self) -> f64 {
+        self.stellar_properties.iter().map(|star| star.mass).sum()
+    }
+
+    pub fn total_luminosity(&self) -> f64 {
+        self.stellar_properties
+            .iter()
+            .map(|star| star.luminosity)
+            .sum()
+    }
+
+    pub fn habitable_zone(&self) -> Option<HabitableZone> {
+        HabitableZone::from_luminosity(self.total_luminosity())
+    }
+
+    pub fn migrate_stellar_properties(&mut self) -> bool {
+        if !self.stellar_properties.is_empty() {
+            return false;
+        }
+        self.stellar_properties = StellarProperties::generate(self.center_type, self.seed());
+        true
+    }
+
+    pub fn galaxy_position(&self) -> Vector3<f64> {
+        galaxy_position(&self.coords, &self.offset)
+    }
+
+    pub fn distance_to(&self, other: &System) -> f64 {
+        (other.galaxy_position() - self.galaxy_position()).norm()
+    }
+
+    pub fn add_body(&mut self, body: Body) -> Result<Uuid> {
+        if self.entities.contains(&body.uuid) {
+            return Err(Error::DuplicateBodyUuid);
+        }
+        if let Some(parent_uuid) = body.parent_uuid {
+            let parent_type = self
+                .entities
+                .body_types
+                .get(&parent_uuid)
+                .ok_or(Error::NoBodyForUuid)?;
+            if !body.body_type.can_orbit(*parent_type) {
+                return Err(Error::InvalidBodyParent);
+            }
+        }
+        let uuid = self.entities.spawn(body.uuid);
+        self.entities.body_types.insert(uuid, body.body_type);
+        self.entities.positions.insert(uuid, body.coords);
+        self.entities.velocities.insert(uuid, body.velocity);
+        if let Some(parent_uuid) = body.parent_uuid {
+            self.entities.parents.insert(uuid, parent_uuid);
+        }
+        if let Some(environment) = body.environment {
+            self.entities.environments.insert(uuid, environment);
+        }
+        self.generate_environment(&uuid);
+        self.generate_deposits(&uuid);
+        Ok(uuid)
+    }
+
+    fn generate_environment(&mut self, uuid: &Uuid) -> bool {
+        let Some(body_type) = self.entities.body_types.get(uuid).copied() else {
+            return false;
+        };
+        if self.entities.environments.contains_key(uuid) {
+            return false;
+        }
+        let distance = self
+            .absolute_coords(uuid)
+            .map_or(0., |coords| coords.map(|coord| coord as f64).norm());
+        let (high, low) = uuid.as_u64_pair();
+        let Some(environment) = PlanetEnvironment::generate(
+            body_type,
+            self.total_luminosity(),
+            distance,
+            self.seed() ^ high ^ low,
+        ) else {
+            return false;
+        };
+
+        self.entities
+            .masses
+            .insert(*uuid, environment.mass() as f32);
+        self.entities.environments.insert(*uuid, environment);
+        true
+    }
+
+    fn generate_deposits(&mut self, uuid: &Uuid) -> bool {
+        let Some(body_type) = self.entities.body_types.get(uuid).copied() else {
+            return false;
+        };
+        if self.entities.deposits.contains_key(uuid) {
+            return false;
+        }
+        // Rotated so that deposits don't draw the same numbers as the
+        // environment.
+        let (high, low) = uuid.as_u64_pair();
+        let Some(deposits) = generate_deposits(
+            body_type,
+            self.entities.environments.get(uuid),
+            self.seed() ^ high.rotate_left(1) ^ low,
+        ) else {
+            return false;
+        };
+        self.entities.deposits.insert(*uuid, deposits);
+        true
+    }
+
+    pub fn body_radius(&self, uuid: &Uuid) -> Option<f32> {
+        match self.entities.environments.get(uuid) {
+            Some(environment) => Some(environment.radius as f32),
+            None => self.entities.body_types.get(uuid)?.default_radius(),
+        }
+    }
+
+    pub fn remove_body(&mut self, uuid: &Uuid) -> Option<Body> {
+        let removed = self.body(uuid)?;
+        let mut children: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (child, parent) in self.entities.parents.iter() {
+            if self.entities.body_types.contains_key(child) {
+                children.entry(*parent).or_default().push(*child);
+            }
+        }
+
+        let mut pending = vec![*uuid];
+        while let Some(uuid) = pending.pop() {
+            if self.entities.despawn(&uuid) {
+                pending.extend(children.remove(&uuid).unwrap_or_default());
+            }
+        }
+        Some(removed)
+    }
+
+    pub fn children(&self, uuid: &Uuid) -> Vec<Uuid> {
+        self.entities
+            .iter()
+            .filter(|child| self.entities.parents.get(child) == Some(uuid))
+            .copied()
+            .collect()
+    }
+
+    pub fn absolute_coords(&self, uuid: &Uuid) -> Option<Vector3<f32>> {
+        let mut coords = *self.entities.positions.get(uuid)?;
+        let mut current = *uuid;
+        for _ in 0..self.entities.len() {
+            let Some(parent_coords) = self
+                .entities
+                .parents
+                .get(&current)
+                .and_then(|parent| Some((*parent, self.entities.positions.get(parent)?)))
+            else {
+                return Some(coords);
+            };
+            coords += parent_coords.1;
+            current = parent_coords.0;
+        }
+        Some(coords)
+    }
+
+    pub fn absolute_velocity(&self, uuid: &Uuid) -> Option<Vector3<f32>> {
+        let mut velocity = *self.entities.velocities.get(uuid)?;
+        let mut current = *uuid;
+        for _ in 0..self.entities.len() {
+            let Some(parent) = self.entities.parents.get(&current) else {
+                return Some(velocity);
+            };
+            velocity += self
+                .entities
+                .velocities
+                .get(parent)
+                .copied()
+                .unwrap_or_default();
+            current = *parent;
+        }
+        Some(velocity)
+    }
+
+    pub fn body(&self, uuid: &Uuid) -> Option<Body> {
+        Some(Body {
+            uuid: *uuid,
+            body_type: *self.entities.body_types.get(uuid)?,
+            parent_uuid: self.entities.parents.get(uuid).copied(),
+            coords: self
+                .entities
+                .positions
+                .get(uuid)
+                .copied()
+                .unwrap_or_default(),
+            velocity: self
+                .entities
+                .velocities
+                .get(uuid)
+                .copied()
+                .unwrap_or_default(),
+            environment: self.entities.environments.get(uuid).cloned(),
+        })
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = Body> + '_ {
+        self.entities.iter().filter_map(|uuid| self.body(uuid))
+    }
+
+    pub fn view_for(&self, player_uuid: Uuid) -> SystemView {
+        let mut entities = self.entities.without_blocks();
+        entities
+            .inventories
+            .retain(|uuid, _inventory| self.entities.owners.get(uuid) == Some(&player_uuid));
+        for jobs in entities.productions.values_mut() {
+            jobs.retain(|job| job.owner_uuid == player_uuid);
+        }
+        entities.productions.retain(|_uuid, jobs| !jobs.is_empty());
+        SystemView {
+            coords: self.coords,
+            offset: self.offset,
+            center_type: self.center_type,
+            stellar_properties: self.stellar_properties.clone(),
+            entities,
+            simulated_time: self.simulated_time,
+        }
+    }
+
+    pub fn body_count(&self) -> usize {
+        self.entities.body_types.len()
     }
+
+    pub fn spawn_player(&mut self, uuid: Uuid, player: &Player) {
+        self.entities.spawn(uuid);
+        self.entities.players.insert(uuid);
+        self.entities.positions.insert(uuid, player.coords);
+        self.entities.velocities.insert(uuid, Vector3::default());
+        self.entities.masses.insert(uuid, PLAYER_MASS);
+        self.entities.healths.insert(uuid, player.health);
+        self.entities.owners.insert(uuid, uuid);
+        if let Some(station_uuid) = player.docked_station_uuid {
+            self.attach(&uuid, &station_uuid);
+        }
+    }
+
+    pub fn attach(&mut self, uuid: &Uuid, parent_uuid: &Uuid) -> bool {
+        let (Some(coords), Some(parent_coords)) = (
+            self.absolute_coords(uuid),
+            self.absolute_coords(parent_uuid),
+        ) else {
+            return false;
+        };
+        self.entities.parents.insert(*uuid, *parent_uuid);
+        self.entities
+            .positions
+            .insert(*uuid, coords - parent_coords);
+        self.entities.velocities.insert(*uuid, Vector3::default());
+        true
+    }
+
+    pub fn detach(&mut self, uuid: &Uuid) {
+        let (Some(coords), Some(velocity)) =
+            (self.absolute_coords(uuid), self.absolute_velocity(uuid))
+        else {
+            return;
+        };
+        self.entities.parents.remove(uuid);
+        self.entities.positions.insert(*uuid, coords);
+        self.entities.velocities.insert(*uuid, velocity);
+    }
+
+    pub fn spawn_structure(
+        &mut self,
+        owner_uuid: Uuid,
+        position: Vector3<f32>,
+        structure: Structure,
+    ) -> Uuid {
+        let uuid = self.entities.spawn(Uuid::new_v4());
+        self.entities.positions.insert(uuid, position);
+        self.entities.velocities.insert(uuid, Vector3::default());
+        self.entities
+            .orientations
+            .insert(uuid, UnitQuaternion::identity());
+        self.entities
+            .angular_velocities
+            .insert(uuid, Vector3::default());
+        self.entities.masses.insert(uuid, structure.mass());
+        self.entities.owners.insert(uuid, owner_uuid);
+        self.entities.inventories.insert(
+            uuid,
+            Inventory::new(structure.max_cargo_mass(), structure.max_cargo_volume()),
+        );
+        self.entities.structures.insert(uuid, structure);
+        uuid
+    }
+
+    pub fn cargo(&self, structure_uuid: &Uuid) -> Result<Inventory> {
+        let structure = self
+            .entities
+            .structures
+            .get(structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        let mut cargo = self
+            .entities
+            .inventories
+            .get(structure_uuid)
+            .cloned()
+            .unwrap_or_default();
+        cargo.max_mass = structure.max_cargo_mass();
+        cargo.max_volume = structure.max_cargo_volume();
+        Ok(cargo)
+    }
+
+    pub fn cargo_mut(&mut self, structure_uuid: &Uuid) -> Result<&mut Inventory> {
+        let structure = self
+            .entities
+            .structures
+            .get(structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        let cargo = self
+            .entities
+            .inventories
+            .entry(*structure_uuid)
+            .or_default();
+        cargo.max_mass = structure.max_cargo_mass();
+        cargo.max_volume = structure.max_cargo_volume();
+        Ok(cargo)
+    }
+
+    pub fn set_block(
+        &mut self,
+        structure_uuid: &Uuid,
+        position: Vector3<BlockCoordsRepr>,
+        block_type: Option<BlockType>,
+    ) -> Result<Option<BlockType>> {
+        let structure = self
+            .entities
+            .structures
+            .get_mut(structure_uuid)
+            .ok_or(Error::NoStructureForUuid)?;
+        let previous = structure.set_block(position, block_type);
+        self.entities
+            .masses
+            .insert(*structure_uuid, structure.mass());
+        Ok(previous)
+    }
+
+    pub fn owned_structures<'a>(&'a self, owner_uuid: &'a Uuid) -> impl Iterator<Item = Uuid> + 'a {
+        self.entities
+            .structures
+            .keys()
+            .filter(move |uuid| self.entities.owners.get(uuid) == Some(owner_uuid))
+            .copied()
+    }
+
+    pub fn sync_player(&self, uuid: &Uuid, player: &mut Player) -> bool {
+        if !self.entities.players.contains(uuid) {
+            return false;
+        }
+        if let Some(coords) = self.absolute_coords(uuid) {
+            player.coords = coords;
+        }
+        player.docked_station_uuid = self.entities.parents.get(uuid).copied();
+        if let Some(health) = self.entities.healths.get(uuid) {
+            player.health = *health;
+        }
+        true
+    }
+
+    pub fn migrate_environments(&mut self) -> bool {
+        let uuids: Vec<Uuid> = self.entities.body_types.keys().copied().collect();
+        let mut migrated = false;
+        for uuid in uuids {
+            migrated |= self.generate_environment(&uuid);
+        }
+        migrated
+    }
+
+    pub fn migrate_deposits(&mut self) -> bool {
+        let uuids: Vec<Uuid> = self.entities.body_types.keys().copied().collect();
+        let mut migrated = false;
+        for uuid in uuids {
+            migrated |= self.generate_deposits(&uuid);
+        }
+        migrated
+    }
+
+    pub fn migrate_body_uuids(&mut self) -> bool {
+        let mut migrated = false;
+        for body in self
+            .legacy_bodies
+            .iter_mut()
+            .filter(|body| body.uuid.is_nil())
+        {
+            body.uuid = Uuid::new_v4();
+            migrated = true;
+        }
+        migrated
+    }
+
+    pub fn migrate_legacy_bodies(&mut self) -> bool {
+        if self.legacy_bodies.is_empty() {
+            return false;
+        }
+        // Parents first, so that children find them. Broken parent links are
+        // dropped rather than losing the body.
+        let mut pending = std::mem::take(&mut self.legacy_bodies);
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<Body>, Vec<Body>) = pending.into_iter().partition(|body| {
+                body.parent_uuid
+                    .is_none_or(|parent| self.entities.body_types.contains_key(&parent))
+            });
+            if ready.is_empty() {
+                for mut body in waiting {
+                    body.parent_uuid = None;
+                    let _ = self.add_body(body);
+                }
+                break;
+            }
+            for body in ready {
+                if self.add_body(body.clone()).is_err() {
+                    let _ = self.add_body(Body {
+                        parent_uuid: None,
+                        ..body
+                    });
+                }
+            }
+            pending = waiting;
+        }
+        true
+    }
+
+    pub fn has_unchunked_structures(&self) -> bool {
+        self.entities
+            .structures
+            .keys()
+            .any(|uuid| !self.entities.structure_chunks.contains_key(uuid))
+    }
+
+    pub fn catch_up(&mut self, time: f64) -> Result<()> {
+        let delta = time - self.simulated_time;
+        if delta > CATCH_UP_STEP {
+            // Orbits, drift and production are exact whatever the step, flight
+            // isn't and only covers the last `MAX_CATCH_UP_STEPS` steps.
+            let flown = delta.min(MAX_CATCH_UP_STEPS as f64 * CATCH_UP_STEP);
+            self.entities.integrate_velocities((delta - flown) as f32);
+            let steps = (flown / CATCH_UP_STEP).ceil();
+            let step = (flown / steps) as f32;
+            for _ in 0..steps as u32 {
+                self.fly(step);
+            }
+            advance_orbits(&mut self.entities, delta as f32);
+            let finished = advance_production(&mut self.entities, delta as f32);
+            self.finished_jobs.extend(finished);
+            self.simulated_time = time;
+        } else if delta > 0. {
+            self.update(delta as f32)?;
+        }
+        self.simulated_time = self.simulated_time.max(time);
+        Ok(())
+    }
+
+    fn fly(&mut self, delta: f32) {
+        apply_flight(&mut self.entities, delta);
+        self.entities.integrate_velocities(delta);
+    }
+
+    pub fn stats(&self, uuid: Uuid) -> SystemStats {
+        let distances: Vec<f32> = self
+            .entities
+            .body_types
+            .keys()
+            .filter_map(|uuid| self.absolute_coords(uuid))
+            .map(|coords| coords.norm())
+            .collect();
+        let speeds: Vec<f32> = self
+            .entities
+            .body_types
+            .keys()
+            .filter_map(|uuid| self.entities.velocities.get(uuid))
+            .map(|velocity| velocity.norm())
+            .collect();
+        let mut cargo: BTreeMap<ItemId, u32> = BTreeMap::new();
+        for stack in self
+            .entities
+            .inventories
+            .values()
+            .flat_map(Inventory::stacks)
+        {
+            *cargo.entry(stack.item.clone()).or_default() += stack.quantity;
+        }
+        let mut deposits: BTreeMap<ResourceType, f32> = BTreeMap::new();
+        for deposit in self.entities.deposits.values().flatten() {
+            *deposits.entry(deposit.resource).or_default() += deposit.amount;
+        }
+        SystemStats {
+            uuid,
+            center_type: self.center_type,
+            body_count: self.body_count(),
+            simulated_time: self.simulated_time,
+            max_body_distance: distances.iter().copied().fold(0., f32::max),
+            mean_body_speed: if speeds.is_empty() {
+                0.
+            } else {
+                speeds.iter().sum::<f32>() / speeds.len() as f32
+            },
+            structure_count: self.entities.structures.len(),
+            production_jobs: self.entities.productions.values().map(Vec::len).sum(),
+            cargo,
+            deposits,
+        }
+    }
+
+    pub fn summary(&self, uuid: Uuid) -> SystemSummary {
+        SystemSummary {
+            uuid,
+            coords: self.coords,
+            offset: self.offset,
+            center_type: self.center_type,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SystemSummary {
+    pub uuid: Uuid,
+    pub coords: Vector3<GalaxyCoordsRepr>, // parsec
+    pub offset: Vector3<GalaxyOffsetRepr>, // au
+    pub center_type: CenterType,
+}
+
+impl SystemSummary {
+    pub fn galaxy_position(&self) -> Vector3<f64> {
+        galaxy_position(&self.coords, &self.offset)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SystemStats {
+    pub uuid: Uuid,
+    pub center_type: CenterType,
+    pub body_count: usize,
+    pub simulated_time: f64,    // second
+    pub max_body_distance: f32, // from the center
+    pub mean_body_speed: f32,   // relative to the parent
+    pub structure_count: usize,
+    pub production_jobs: usize,                // queued or running
+    pub cargo: BTreeMap<ItemId, u32>,          // in the structures
+    pub deposits: BTreeMap<ResourceType, f32>, // kg left in the bodies
 }
 
 impl Temporal for System {
     fn update(&mut self, delta: f32) -> Result<()> {
-        for body in self.bodies.iter_mut() {
-            body.update(delta)?;
-        }
+        self.fly(delta);
+        advance_orbits(&mut self.entities, delta);
+
+        let mut spatial_hash = std::mem::take(&mut self.spatial_hash);
+        let contacts = detect_collisions(self, &mut spatial_hash);
+        self.spatial_hash = spatial_hash;
+        let impacts = resolve_collisions(self, contacts);
+        self.collisions.extend(impacts);
+        let finished = advance_production(&mut self.entities, delta);
+        self.finished_jobs.extend(finished);
+        self.simulated_time += delta as f64;
         Ok(())
     }
 }
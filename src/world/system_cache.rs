@@ -0,0 +1,73 @@
+use crate::config::SystemCacheConfig;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+pub struct SystemCache {
+    pub config: SystemCacheConfig,
+    last_used: HashMap<Uuid, Instant>,
+    dirty: HashSet<Uuid>,
+}
+
+impl SystemCache {
+    pub fn new(config: SystemCacheConfig) -> Self {
+        Self {
+            config,
+            last_used: HashMap::new(),
+            dirty: HashSet::new(),
+        }
+    }
+
+    pub fn touch(&mut self, uuid: Uuid, now: Instant) {
+        self.last_used.insert(uuid, now);
+    }
+
+    pub fn mark_dirty(&mut self, uuid: Uuid) {
+        self.dirty.insert(uuid);
+    }
+
+    pub fn mark_clean(&mut self, uuid: &Uuid) {
+        self.dirty.remove(uuid);
+    }
+
+    pub fn is_dirty(&self, uuid: &Uuid) -> bool {
+        self.dirty.contains(uuid)
+    }
+
+    pub fn forget(&mut self, uuid: &Uuid) {
+        self.last_used.remove(uuid);
+        self.dirty.remove(uuid);
+    }
+
+    pub fn eviction_candidates<'a>(
+        &self,
+        loaded: impl Iterator<Item = &'a Uuid>,
+        pinned: &HashSet<Uuid>,
+        now: Instant,
+    ) -> Vec<Uuid> {
+        let mut loaded_count: usize = 0;
+        let mut unpinned: Vec<(Instant, Uuid)> = Vec::new();
+        for uuid in loaded {
+            loaded_count += 1;
+            if !pinned.contains(uuid) {
+                let last_used = self.last_used.get(uuid).copied().unwrap_or(now);
+                unpinned.push((last_used, *uuid));
+            }
+        }
+        unpinned.sort();
+
+        let idle_timeout =
+            Duration::try_from_secs_f64(self.config.idle_timeout.max(0.)).unwrap_or(Duration::MAX);
+        let excess = loaded_count.saturating_sub(self.config.max_loaded_systems);
+        unpinned
+            .into_iter()
+            .enumerate()
+            .take_while(|(rank, (last_used, _uuid))| {
+                *rank < excess || now.saturating_duration_since(*last_used) > idle_timeout
+            })
+            .map(|(_rank, (_last_used, uuid))| uuid)
+            .collect()
+    }
+}
@@ -0,0 +1,105 @@
+use super::system::SystemSummary;
+use nalgebra::Vector3;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub const SECTOR_SIZE: f64 = 500.; // parsec
+
+pub struct SystemIndex {
+    sectors: HashMap<Vector3<i32>, Vec<Uuid>>,
+    summaries: HashMap<Uuid, SystemSummary>,
+}
+
+impl SystemIndex {
+    pub fn new() -> Self {
+        Self {
+            sectors: HashMap::new(),
+            summaries: HashMap::new(),
+        }
+    }
+
+    fn sector_of(position: &Vector3<f64>) -> Vector3<i32> {
+        position.map(|coord| (coord / SECTOR_SIZE).floor() as i32)
+    }
+
+    pub fn insert(&mut self, summary: SystemSummary) {
+        self.remove(&summary.uuid);
+        self.sectors
+            .entry(Self::sector_of(&summary.galaxy_position()))
+            .or_default()
+            .push(summary.uuid);
+        self.summaries.insert(summary.uuid, summary);
+    }
+
+    pub fn remove(&mut self, uuid: &Uuid) -> Option<SystemSummary> {
+        let summary = self.summaries.remove(uuid)?;
+        let sector = Self::sector_of(&summary.galaxy_position());
+        if let Some(uuids) = self.sectors.get_mut(&sector) {
+            uuids.retain(|other| other != uuid);
+            if uuids.is_empty() {
+                self.sectors.remove(&sector);
+            }
+        }
+        Some(summary)
+    }
+
+    pub fn get(&self, uuid: &Uuid) -> Option<&SystemSummary> {
+        self.summaries.get(uuid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.summaries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summaries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SystemSummary> {
+        self.summaries.values()
+    }
+
+    pub fn within_radius(&self, center: &Vector3<f64>, radius: f64) -> Vec<&SystemSummary> {
+        let min_sector = Self::sector_of(&center.add_scalar(-radius));
+        let max_sector = Self::sector_of(&center.add_scalar(radius));
+
+        let span = (max_sector - min_sector).map(|extent| extent as i64 + 1);
+        let candidates: Vec<&Uuid> = if span.x * span.y * span.z > self.sectors.len() as i64 {
+            self.sectors.values().flatten().collect()
+        } else {
+            let mut candidates = Vec::new();
+            for x in min_sector.x..=max_sector.x {
+                for y in min_sector.y..=max_sector.y {
+                    for z in min_sector.z..=max_sector.z {
+                        if let Some(uuids) = self.sectors.get(&Vector3::new(x, y, z)) {
+                            candidates.extend(uuids);
+                        }
+                    }
+                }
+            }
+            candidates
+        };
+
+        let mut found: Vec<(f64, &SystemSummary)> = candidates
+            .into_iter()
+            .filter_map(|uuid| {
+                let summary = &self.summaries[uuid];
+                let distance = (summary.galaxy_position() - center).norm();
+                (distance <= radius).then_some((distance, summary))
+            })
+            .collect();
+
+        found.sort_by(|(distance_a, summary_a), (distance_b, summary_b)| {
+            distance_a
+                .total_cmp(distance_b)
+                .then(summary_a.uuid.cmp(&summary_b.uuid))
+        });
+        found.into_iter().map(|(_, summary)| summary).collect()
+    }
+}
+
+impl Default for SystemIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}